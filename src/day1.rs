@@ -1,56 +1,357 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
+use std::{convert::TryFrom, fmt::Debug, num::ParseIntError, str::FromStr};
 
 pub fn get_part1() -> Result<i64> {
-    compute(include_str!("data\\day1-input.txt"), 2020, find_pair)
+    compute(include_str!("data\\day1-input.txt"), 2020, 2)
 }
 pub fn get_part2() -> Result<i64> {
-    compute(include_str!("data\\day1-input.txt"), 2020, find_triplet)
+    compute(include_str!("data\\day1-input.txt"), 2020, 3)
 }
 
-fn compute(s: &str, sum: i32, f: impl FnOnce(&str, i32) -> Result<Vec<i32>>) -> Result<i64> {
-    f(s, sum).map(|vec| vec.iter().fold(1i64, |u, v| u * *v as i64))
+fn compute<T: Entry>(s: &str, sum: T, k: usize) -> Result<T> {
+    product(&find_k_sum(&parse(s)?, k, sum)?.values)
 }
 
-// JB 2020-12-30: so this is pretty inelegant; apparently itertools::tuple_combinations
-// can build us a nice tuple, which would be interesting to write, but is less interesting
-// to use.
-// ¯\_(ツ)_/¯
+fn parse<T: Entry>(s: &str) -> Result<Vec<T>> {
+    Ok(s.lines()
+        .map(|l| l.parse::<T>())
+        .collect::<Result<Vec<_>, _>>()?)
+}
 
-fn find_pair(s: &str, sum: i32) -> Result<Vec<i32>> {
-    let vec: Result<Vec<_>, _> = s.lines().map(|l| l.parse::<i32>()).collect();
-    let vec = vec?;
-    if vec.len() < 2 {
-        bail!("Not enough members to make a pair");
-    }
-    for (idx, n) in vec.iter().enumerate() {
-        let rest = &vec[idx..];
-        for m in rest {
-            if m + n == sum {
-                return Ok(vec![*n, *m]);
+/// An integer type which can be an expense report entry. Arithmetic is checked, so that
+/// overflow is never silently wrapped.
+pub trait Entry: Copy + Ord + Debug + FromStr<Err = ParseIntError> {
+    const ZERO: Self;
+    const ONE: Self;
+    fn overflowing_add(self, rhs: Self) -> (Self, bool);
+    fn overflowing_sub(self, rhs: Self) -> (Self, bool);
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn to_usize(self) -> Option<usize>;
+}
+macro_rules! impl_entry {
+    ($($t:ty),*) => {
+        $(impl Entry for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            fn overflowing_add(self, rhs: Self) -> (Self, bool) {
+                <$t>::overflowing_add(self, rhs)
+            }
+            fn overflowing_sub(self, rhs: Self) -> (Self, bool) {
+                <$t>::overflowing_sub(self, rhs)
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+            fn to_usize(self) -> Option<usize> {
+                usize::try_from(self).ok()
             }
+        })*
+    };
+}
+impl_entry!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Multiplies the entries together, failing if the product does not fit in `T`.
+pub fn product<T: Entry>(values: &[T]) -> Result<T> {
+    values.iter().try_fold(T::ONE, |u, &v| {
+        u.checked_mul(v)
+            .ok_or_else(|| anyhow!("Product of {:?} overflows", values))
+    })
+}
+
+/// Distinct entries of the expense report, in report order, with their positions in the report.
+#[derive(Debug, PartialEq)]
+pub struct Solution<T> {
+    pub indices: Vec<usize>,
+    pub values: Vec<T>,
+}
+impl<T: Entry> Solution<T> {
+    fn from_indices(values: &[T], mut indices: Vec<usize>) -> Solution<T> {
+        indices.sort_unstable();
+        let values = indices.iter().map(|&idx| values[idx]).collect();
+        Solution { indices, values }
+    }
+}
+
+/// Finds `k` entries at distinct positions which sum to `target`.
+pub fn find_k_sum<T: Entry>(values: &[T], k: usize, target: T) -> Result<Solution<T>> {
+    if values.len() < k {
+        bail!("Not enough members to make a set of {}", k);
+    }
+    let mut found = None;
+    visit_k_sums(values, k, target, |indices| {
+        found = Some(indices.to_vec());
+        false
+    });
+    found
+        .map(|indices| Solution::from_indices(values, indices))
+        .ok_or_else(|| anyhow!("No set of {} members sums to the expected value", k))
+}
+/// Finds every set of `k` distinct positions whose entries sum to `target`, ordered by position.
+pub fn find_all_k_sums<T: Entry>(values: &[T], k: usize, target: T) -> Vec<Solution<T>> {
+    let mut output = Vec::new();
+    visit_k_sums(values, k, target, |indices| {
+        output.push(Solution::from_indices(values, indices.to_vec()));
+        true
+    });
+    output.sort_unstable_by(|a, b| a.indices.cmp(&b.indices));
+    output
+}
+
+/// A sum which may not fit in `T`: `low`, plus `carry` times the size of `T`'s range. Since
+/// `low` covers exactly one range, comparing `carry` then `low` compares the sums.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Wide<T> {
+    carry: i64,
+    low: T,
+}
+impl<T: Entry> Wide<T> {
+    fn new(low: T) -> Wide<T> {
+        Wide { carry: 0, low }
+    }
+    fn add(self, rhs: T) -> Wide<T> {
+        let (low, overflowed) = self.low.overflowing_add(rhs);
+        let carry = match overflowed {
+            false => 0,
+            true if rhs > T::ZERO => 1,
+            true => -1,
+        };
+        Wide {
+            carry: self.carry + carry,
+            low,
+        }
+    }
+    fn sub(self, rhs: T) -> Wide<T> {
+        let (low, overflowed) = self.low.overflowing_sub(rhs);
+        let carry = match overflowed {
+            false => 0,
+            true if rhs > T::ZERO => -1,
+            true => 1,
+        };
+        Wide {
+            carry: self.carry + carry,
+            low,
         }
     }
-    bail!("No pair of members sums to the expected value");
 }
 
-fn find_triplet(s: &str, sum: i32) -> Result<Vec<i32>> {
-    let vec: Result<Vec<_>, _> = s.lines().map(|l| l.parse::<i32>()).collect();
-    let vec = vec?;
-    if vec.len() < 3 {
-        bail!("Not enough members to make a triplet");
-    }
-    for (m_idx, m) in vec.iter().enumerate() {
-        let r1 = &vec[m_idx..];
-        for (n_idx, n) in r1.iter().enumerate() {
-            let r2 = &vec[n_idx..];
-            for o in r2 {
-                if m + n + o == sum {
-                    return Ok(vec![*n, *m, *o]);
+/// The remaining target is tracked as a `Wide`, so that sets are found even when a partial sum
+/// does not fit in `T`.
+fn visit_k_sums<T: Entry>(
+    values: &[T],
+    k: usize,
+    target: T,
+    mut visit: impl FnMut(&[usize]) -> bool,
+) {
+    /// Finds the positions of every pair in the sorted `order` which sums to `target`.
+    fn pairs<T: Entry>(
+        value: impl Fn(usize) -> T,
+        len: usize,
+        target: Wide<T>,
+    ) -> Vec<(usize, usize)> {
+        let mut output = Vec::new();
+        if len < 2 {
+            return output;
+        }
+        let (mut lo, mut hi) = (0, len - 1);
+        while lo < hi {
+            let sum = Wide::new(value(lo)).add(value(hi));
+            if sum < target {
+                lo += 1;
+            } else if sum > target {
+                hi -= 1;
+            } else if value(lo) == value(hi) {
+                // everything in between has the same value, so pairs up with everything else
+                for a in lo..hi {
+                    output.extend((a + 1..=hi).map(|b| (a, b)));
+                }
+                break;
+            } else {
+                let mut lo_end = lo;
+                while value(lo_end + 1) == value(lo) {
+                    lo_end += 1;
+                }
+                let mut hi_start = hi;
+                while value(hi_start - 1) == value(hi) {
+                    hi_start -= 1;
+                }
+                for a in lo..=lo_end {
+                    output.extend((hi_start..=hi).map(|b| (a, b)));
+                }
+                lo = lo_end + 1;
+                hi = hi_start - 1;
+            }
+        }
+        output
+    }
+    /// Searches `order`, a list of indices sorted by value, taking from left to right so that
+    /// no index is used twice. Returns `false` once `visit` asks to stop.
+    fn search<T: Entry>(
+        values: &[T],
+        order: &[usize],
+        k: usize,
+        target: Wide<T>,
+        chosen: &mut Vec<usize>,
+        visit: &mut dyn FnMut(&[usize]) -> bool,
+    ) -> bool {
+        let value = |pos: usize| values[order[pos]];
+        let mut candidates: Box<dyn Iterator<Item = Vec<usize>>> = match k {
+            0 => {
+                return target != Wide::new(T::ZERO) || visit(chosen);
+            }
+            1 => Box::new(
+                (0..order.len())
+                    .filter(|&pos| Wide::new(value(pos)) == target)
+                    .map(|pos| vec![order[pos]]),
+            ),
+            2 => Box::new(
+                pairs(value, order.len(), target)
+                    .into_iter()
+                    .map(|(a, b)| vec![order[a], order[b]]),
+            ),
+            _ => {
+                for pos in 0..order.len() {
+                    let remaining = target.sub(value(pos));
+                    chosen.push(order[pos]);
+                    let more = search(values, &order[pos + 1..], k - 1, remaining, chosen, visit);
+                    chosen.pop();
+                    if !more {
+                        return false;
+                    }
+                }
+                return true;
+            }
+        };
+        candidates.all(|found| {
+            let len = chosen.len();
+            chosen.extend(found);
+            let more = visit(chosen);
+            chosen.truncate(len);
+            more
+        })
+    }
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&idx| values[idx]);
+    search(
+        values,
+        &order,
+        k,
+        Wide::new(target),
+        &mut Vec::with_capacity(k),
+        &mut visit,
+    );
+}
+
+/// Subset sums index a table by sum, so need non-negative members and target.
+fn as_index<T: Entry>(value: T) -> Result<usize> {
+    if value < T::ZERO {
+        bail!(
+            "Subset sums need non-negative members and target, got {:?}",
+            value
+        );
+    }
+    value
+        .to_usize()
+        .ok_or_else(|| anyhow!("{:?} is too large for a subset sum", value))
+}
+/// The most memory, in bytes, that a subset sum table may take.
+const MAX_TABLE_BYTES: usize = 1 << 28;
+/// The length of each row of a subset sum table with `rows` rows of `cell_bytes`-sized cells,
+/// failing rather than allocating more than `MAX_TABLE_BYTES`.
+fn table_width(target: usize, rows: usize, cell_bytes: usize) -> Result<usize> {
+    target
+        .checked_add(1)
+        .filter(|&width| {
+            width
+                .checked_mul(rows)
+                .and_then(|cells| cells.checked_mul(cell_bytes))
+                .is_some_and(|bytes| bytes <= MAX_TABLE_BYTES)
+        })
+        .ok_or_else(|| anyhow!("Target {} is too large for a subset sum", target))
+}
+/// Counts the non-empty sets of positions, of any size, whose entries sum to `target`.
+pub fn count_subset_sums<T: Entry>(values: &[T], target: T) -> Result<u128> {
+    let target = as_index(target)?;
+    // ways[s] is the number of subsets of the entries so far which sum to s
+    let mut ways = vec![0u128; table_width(target, 1, std::mem::size_of::<u128>())?];
+    ways[0] = 1;
+    for &v in values {
+        let v = as_index(v)?;
+        for s in (v..=target).rev() {
+            ways[s] = ways[s]
+                .checked_add(ways[s - v])
+                .ok_or_else(|| anyhow!("Too many subsets to count"))?;
+        }
+    }
+    Ok(if target == 0 {
+        ways[0] - 1
+    } else {
+        ways[target]
+    })
+}
+/// Finds up to `limit` non-empty sets of positions, of any size, whose entries sum to `target`.
+pub fn find_subset_sums<T: Entry>(
+    values: &[T],
+    target: T,
+    limit: usize,
+) -> Result<Vec<Solution<T>>> {
+    struct Walk<'a, T> {
+        values: &'a [T],
+        sizes: Vec<usize>,
+        // reachable[i][s] if some subset of values[i..] sums to s
+        reachable: Vec<Vec<bool>>,
+        chosen: Vec<usize>,
+        output: Vec<Solution<T>>,
+        limit: usize,
+    }
+    impl<T: Entry> Walk<'_, T> {
+        fn walk(&mut self, idx: usize, remaining: usize) {
+            if self.output.len() >= self.limit {
+                return;
+            }
+            if idx == self.values.len() {
+                if !self.chosen.is_empty() {
+                    let indices = self.chosen.clone();
+                    self.output
+                        .push(Solution::from_indices(self.values, indices));
                 }
+                return;
+            }
+            let v = self.sizes[idx];
+            if v <= remaining && self.reachable[idx + 1][remaining - v] {
+                self.chosen.push(idx);
+                self.walk(idx + 1, remaining - v);
+                self.chosen.pop();
             }
+            if self.reachable[idx + 1][remaining] {
+                self.walk(idx + 1, remaining);
+            }
+        }
+    }
+    let target = as_index(target)?;
+    let sizes = values
+        .iter()
+        .map(|&v| as_index(v))
+        .collect::<Result<Vec<_>>>()?;
+    let width = table_width(target, values.len() + 1, 1)?;
+    let mut reachable = vec![vec![false; width]; values.len() + 1];
+    reachable[values.len()][0] = true;
+    for (idx, &v) in sizes.iter().enumerate().rev() {
+        for s in 0..=target {
+            reachable[idx][s] = reachable[idx + 1][s] || (s >= v && reachable[idx + 1][s - v]);
         }
     }
-    bail!("No triplet of members sums to the desired value");
+    let mut walk = Walk {
+        values,
+        sizes,
+        reachable,
+        chosen: Vec::new(),
+        output: Vec::new(),
+        limit,
+    };
+    if walk.reachable[0][target] {
+        walk.walk(0, target);
+    }
+    Ok(walk.output)
 }
 
 #[cfg(test)]
@@ -62,6 +363,12 @@ mod tests {
 299
 675
 1456";
+    fn find_pair(s: &str, sum: i32) -> Result<Vec<i32>> {
+        find_k_sum(&parse(s)?, 2, sum).map(|s| s.values)
+    }
+    fn find_triplet(s: &str, sum: i32) -> Result<Vec<i32>> {
+        find_k_sum(&parse(s)?, 3, sum).map(|s| s.values)
+    }
     #[test]
     fn no_solution_for_short_input() {
         assert!(find_pair("", 2020).is_err());
@@ -80,7 +387,7 @@ mod tests {
     }
     #[test]
     fn computes_example() {
-        assert_eq!(514579, compute(EXAMLPE_INPUT, 2020, find_pair).unwrap());
+        assert_eq!(514579, compute(EXAMLPE_INPUT, 2020, 2).unwrap());
     }
     #[test]
     fn finds_part2_example() {
@@ -88,4 +395,133 @@ mod tests {
         val.sort();
         assert_eq!(vec!(366, 675, 979), val);
     }
+    #[test]
+    fn reports_indices() {
+        assert_eq!(
+            Solution {
+                indices: vec![1, 2, 4],
+                values: vec![979, 366, 675]
+            },
+            find_k_sum(&parse(EXAMLPE_INPUT).unwrap(), 3, 2020).unwrap()
+        );
+    }
+    #[test]
+    fn does_not_reuse_entries() {
+        assert!(find_k_sum(&[1010, 1], 2, 2020).is_err());
+        assert!(find_k_sum(&[1000, 10, 1], 3, 2010).is_err());
+        assert_eq!(
+            vec![1010, 1010],
+            find_k_sum(&[1010, 1, 1010], 2, 2020).unwrap().values
+        );
+    }
+    #[test]
+    fn finds_other_sizes() {
+        let values = [5, -3, 8, 1, 4];
+        assert_eq!(vec![8], find_k_sum(&values, 1, 8).unwrap().values);
+        assert_eq!(vec![5, -3, 1, 4], find_k_sum(&values, 4, 7).unwrap().values);
+        assert!(find_k_sum(&values, 0, 0).unwrap().values.is_empty());
+        assert!(find_k_sum(&values, 6, 15).is_err());
+    }
+    #[test]
+    fn finds_all_k_sums() {
+        let expected = vec![
+            Solution {
+                indices: vec![0, 1],
+                values: vec![1010, 1010],
+            },
+            Solution {
+                indices: vec![0, 3],
+                values: vec![1010, 1010],
+            },
+            Solution {
+                indices: vec![1, 3],
+                values: vec![1010, 1010],
+            },
+        ];
+        assert_eq!(expected, find_all_k_sums(&[1010, 1010, 7, 1010], 2, 2020));
+        let pairs = find_all_k_sums(&[1, 3, 1, 3, 2, 2], 2, 4)
+            .into_iter()
+            .map(|s| s.indices)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![vec![0, 1], vec![0, 3], vec![1, 2], vec![2, 3], vec![4, 5]],
+            pairs
+        );
+    }
+    #[test]
+    fn finds_all_example_triplets() {
+        let values = parse(EXAMLPE_INPUT).unwrap();
+        assert_eq!(1, find_all_k_sums(&values, 3, 2020).len());
+        assert_eq!(4, find_all_k_sums(&[1, 1, 1, 1], 3, 3).len());
+        assert!(find_all_k_sums(&values, 7, 2020).is_empty());
+    }
+    #[test]
+    fn counts_subset_sums() {
+        let values = parse(EXAMLPE_INPUT).unwrap();
+        assert_eq!(2, count_subset_sums(&values, 2020).unwrap());
+        assert_eq!(3, count_subset_sums(&[1, 1, 2, 3], 3).unwrap());
+        assert_eq!(3, count_subset_sums(&[0, 0, 5], 0).unwrap());
+        assert!(count_subset_sums(&[-1, 2], 1).is_err());
+        assert!(count_subset_sums::<u64>(&[1], u64::MAX).is_err());
+        assert!(count_subset_sums::<i128>(&[1], 1_000_000_000_000).is_err());
+    }
+    #[test]
+    fn finds_subset_sums() {
+        let values = parse(EXAMLPE_INPUT).unwrap();
+        let found = find_subset_sums(&values, 2020, usize::MAX).unwrap();
+        assert_eq!(
+            vec![vec![0, 3], vec![1, 2, 4]],
+            found.into_iter().map(|s| s.indices).collect::<Vec<_>>()
+        );
+        let found = find_subset_sums(&[1, 1, 2, 3], 3, usize::MAX).unwrap();
+        assert_eq!(3, found.len());
+        assert!(found.iter().all(|s| s.values.iter().sum::<i32>() == 3));
+        assert_eq!(2, find_subset_sums(&[1, 1, 2, 3], 3, 2).unwrap().len());
+        assert!(find_subset_sums(&[1, 1], 5, 10).unwrap().is_empty());
+        assert!(find_subset_sums::<u64>(&[1], u64::MAX, 1).is_err());
+        assert!(find_subset_sums::<u64>(&[1; 100], 1_000_000_000_000, 1).is_err());
+    }
+    #[test]
+    fn parses_wide_entries() {
+        let values = parse::<i128>("100000000000000000000\n-1").unwrap();
+        assert_eq!(
+            vec![100000000000000000000, -1],
+            find_k_sum(&values, 2, 99999999999999999999).unwrap().values
+        );
+        assert!(parse::<u8>("256").is_err());
+    }
+    #[test]
+    fn product_fails_on_overflow() {
+        assert_eq!(514579, compute::<i64>(EXAMLPE_INPUT, 2020, 2).unwrap());
+        assert_eq!(241861950, compute::<i32>(EXAMLPE_INPUT, 2020, 3).unwrap());
+        assert!(compute::<i16>(EXAMLPE_INPUT, 2020, 2).is_err());
+        assert!(product(&[i128::MAX, 2]).is_err());
+        assert_eq!(1, product::<u8>(&[]).unwrap());
+    }
+    #[test]
+    fn finds_sums_past_overflow() {
+        assert_eq!(
+            vec![100, 27],
+            find_k_sum::<i8>(&[100, 100, 27], 2, 127).unwrap().values
+        );
+        // 100 + 100 - 100 = 100, but searching it needs 200, which does not fit
+        assert_eq!(
+            vec![100, 100, -100],
+            find_k_sum::<i8>(&[100, 100, -100], 3, 100).unwrap().values
+        );
+        let values = [2_000_000_000, 2_000_000_000, -2_000_000_000];
+        assert_eq!(
+            vec![0, 1, 2],
+            find_k_sum::<i32>(&values, 3, 2_000_000_000)
+                .unwrap()
+                .indices
+        );
+        assert_eq!(1, find_all_k_sums::<i32>(&values, 3, 2_000_000_000).len());
+        // 200 + 200 wraps to 144, which must not count
+        assert!(find_k_sum::<u8>(&[200, 200, 100], 2, 144).is_err());
+        assert_eq!(
+            vec![0, -128, 127],
+            find_k_sum::<i8>(&[0, -1, -128, 127], 3, -1).unwrap().values
+        );
+    }
 }
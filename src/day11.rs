@@ -1,3 +1,4 @@
+use crate::util::automaton::Generations;
 use anyhow::{bail, Error, Result};
 use std::{
     convert::TryFrom,
@@ -63,37 +64,13 @@ impl TryFrom<u8> for SeatState {
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, PartialEq)]
 struct SeatMap(Vec<Vec<SeatState>>);
-struct SeatMapPair(usize, SeatMap, SeatMap);
+type SeatMapPair = Generations<SeatMap>;
 
 impl SeatMapPair {
     pub fn parse(s: &str) -> Result<SeatMapPair> {
-        let first = SeatMap::parse(s)?;
-        let second = SeatMap(first.0.clone());
-        Ok(SeatMapPair(0, first, second))
-    }
-    pub fn current(&self) -> &SeatMap {
-        if self.0 % 2 == 0 {
-            &self.1
-        } else {
-            &self.2
-        }
-    }
-    pub fn step(&mut self, f: impl FnOnce(&SeatMap, &mut SeatMap)) {
-        let (active, next) = if self.0 % 2 == 0 {
-            (&self.1, &mut self.2)
-        } else {
-            (&self.2, &mut self.1)
-        };
-        f(active, next);
-        self.0 += 1;
-    }
-    pub fn is_stable(&self) -> bool {
-        self.0 > 0 && self.1 == self.2
-    }
-    pub fn step_count(&self) -> usize {
-        self.0
+        Ok(Generations::from_initial(SeatMap::parse(s)?))
     }
 }
 
@@ -1,3 +1,5 @@
+use crate::util::modular::Equation;
+
 pub fn part1(s: &str) -> i128 {
     let (current, ids) = parse(s);
     let (id, departure) = ids
@@ -54,91 +56,6 @@ pub fn part2(s: &str) -> i128 {
    ==> 1068781
 */
 
-fn bezout_identity(a: i128, b: i128) -> (i128, i128) {
-    let mut r = (a, b);
-    let mut s = (1, 0);
-    let mut t = (0, 1);
-    fn mutate(x: &mut (i128, i128), quotient: i128) {
-        *x = (x.1, x.0 - quotient * x.1);
-    }
-    loop {
-        if r.1 == 0 {
-            return (s.0, t.0);
-        }
-        let q = r.0 / r.1;
-        mutate(&mut r, q);
-        mutate(&mut s, q);
-        mutate(&mut t, q);
-    }
-}
-
-#[derive(Clone, Copy)]
-struct Equation {
-    divisor: i128,
-    modulus: i128,
-}
-impl Equation {
-    fn new(divisor: i128, modulus: i128) -> Equation {
-        let divisor = divisor % modulus;
-        Equation {
-            divisor: if divisor < 0 {
-                divisor + modulus
-            } else {
-                divisor
-            },
-            modulus,
-        }
-    }
-    fn reduce(u: &Equation, v: &Equation) -> Equation {
-        let &Equation {
-            divisor: a1,
-            modulus: n1,
-        } = u;
-        let &Equation {
-            divisor: a2,
-            modulus: n2,
-        } = v;
-        let (m1, m2) = bezout_identity(n1, n2);
-        debug_assert!(m1 * n1 + m2 * n2 == 1);
-        let x = a1
-            .checked_mul(m2)
-            .and_then(|u| u.checked_mul(n2))
-            .unwrap_or_else(|| panic!("Bad with {}, {}, {}", a1, m2, n2))
-            + a2.checked_mul(m1)
-                .and_then(|u| u.checked_mul(n1))
-                .unwrap_or_else(|| panic!("Bad with {} + {} * {} * {}", a1 * m2 * n2, a2, m1, n1));
-        Equation::new(
-            x,
-            n1.checked_mul(n2)
-                .unwrap_or_else(|| panic!("Bad with {}, {}", n1, n2)),
-        )
-    }
-    fn solve_set(set: impl Iterator<Item = Equation>) -> i128 {
-        set.fold(None, |prev, cur| {
-            if let Some(prev) = prev {
-                Some(dbg!(Equation::reduce(&cur, &prev)))
-            } else {
-                Some(dbg!(cur.clone()))
-            }
-        })
-        .unwrap()
-        .divisor
-    }
-    fn is_satisfied(&self, x: i128) -> bool {
-        x % self.modulus == self.divisor
-    }
-}
-impl std::fmt::Display for Equation {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "x ≡ {} (mod {})", self.divisor, self.modulus)
-    }
-}
-impl std::fmt::Debug for Equation {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "x ≡ {} (mod {})", self.divisor, self.modulus)
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 struct Timestamp(i128);
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -218,56 +135,6 @@ mod tests {
             .all(|&(idx, id)| reference.is_valid_reference(idx, id)))
     }
 
-    #[test]
-    fn gets_bezout() {
-        fn assert_identity(n1: i128, n2: i128) {
-            let (m1, m2) = bezout_identity(n1, n2);
-            assert_eq!(1, m1 * n1 + m2 * n2)
-        }
-        assert_identity(5, 12);
-        assert_identity(3, 4);
-    }
-
-    #[test]
-    fn finds_wikipedia_example() {
-        let u1 = Equation {
-            divisor: 0,
-            modulus: 3,
-        };
-        let u2 = Equation {
-            divisor: 3,
-            modulus: 4,
-        };
-        let u3 = Equation {
-            divisor: 4,
-            modulus: 5,
-        };
-        let u4 = dbg!(Equation::reduce(&u1, &u2));
-        let u5 = dbg!(Equation::reduce(&u3, &u4));
-        assert!(u1.is_satisfied(u4.divisor));
-        assert!(u2.is_satisfied(u4.divisor));
-        assert!(u3.is_satisfied(u5.divisor));
-        assert_eq!(39, u5.divisor)
-    }
-    #[test]
-    fn solves_wikipedia_example() {
-        let eqs = [
-            Equation {
-                divisor: 0,
-                modulus: 3,
-            },
-            Equation {
-                divisor: 3,
-                modulus: 4,
-            },
-            Equation {
-                divisor: 4,
-                modulus: 5,
-            },
-        ];
-        assert_eq!(39, Equation::solve_set(eqs.iter().copied()))
-    }
-
     #[test]
     fn finds_toy_example() {
         // considering the wikipedia example
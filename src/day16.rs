@@ -1,4 +1,4 @@
-use crate::util::split_tuple_2;
+use crate::util::{flat_dedup, split_tuple_2};
 use anyhow::{anyhow, bail, ensure, Error, Result};
 use std::{
     num::ParseIntError,
@@ -17,59 +17,6 @@ pub fn part1(s: &str) -> usize {
         .sum()
 }
 pub fn part2(s: &str) -> usize {
-    /// Takes a vector of alternative-options, and returns a vector containing the single solution
-    fn flat_dedup<T: PartialEq + std::fmt::Debug>(mut vec: Vec<Vec<&T>>) -> Result<Vec<&T>> {
-        /// Deduplicates options from the source multivec if they are unique in the dest single-vec,
-        /// returning `true` if at least one element could be transferred
-        fn transfer<'a, U: PartialEq + std::fmt::Debug>(
-            source: &mut Vec<Vec<&'a U>>,
-            dest: &mut Vec<Option<&'a U>>,
-        ) -> Result<bool> {
-            for unavailable in dest.iter().filter_map(|&i| i) {
-                for options in source.iter_mut() {
-                    options.retain(|&el| el != unavailable);
-                }
-            }
-            let mut did_thing = false;
-            for (idx, single) in source.iter_mut().enumerate().filter(|(_, v)| v.len() == 1) {
-                let dest_cell = dest.get_mut(idx).unwrap();
-                let mut val = single.remove(0);
-                if let Some(existing) = dest_cell.replace(&mut val) {
-                    bail!(
-                        "Unexpectedly replaced an existing unique solution at index {} ({:?}→{:?})",
-                        idx,
-                        existing,
-                        dest_cell
-                    );
-                } else {
-                    did_thing = true;
-                }
-            }
-            Ok(did_thing)
-        }
-        let mut out: Vec<Option<&T>> = std::iter::repeat_with(|| None).take(vec.len()).collect();
-        for (idx, items) in vec.iter_mut().enumerate() {
-            if items.len() == 1 {
-                out[idx] = Some(items.remove(0));
-            }
-        }
-        let mut cycles = 0;
-        while transfer(&mut vec, &mut out)? {
-            cycles += 1;
-        }
-        out.into_iter()
-            .enumerate()
-            .map(|(idx, el)| {
-                el.ok_or(anyhow!(
-                    "Could not resolve duplicates at index {} after {} cycles; {:?}",
-                    idx,
-                    cycles,
-                    vec[idx],
-                ))
-            })
-            .collect::<Result<Vec<_>>>()
-    }
-
     let prob: Problem = s.parse().unwrap();
     let mut rules_for_position = std::iter::repeat(prob.rules.iter().collect::<Vec<_>>())
         .take(prob.my_ticket.0.len())
@@ -1,4 +1,7 @@
+use crate::util::split_tuple_2;
 use anyhow::{anyhow, bail, Result};
+use std::{fmt::Display, io::BufRead, iter::Peekable};
+
 #[derive(Debug, PartialEq)]
 pub struct Policy<'a> {
     source: &'a str,
@@ -18,6 +21,43 @@ pub fn evaluate<T: Validation>(s: &str) -> usize {
         .count()
 }
 
+/// Every line of a password database, with its verdict under each validation.
+pub fn report(s: &str) -> impl Iterator<Item = LineReport<'_>> {
+    s.lines().enumerate().map(|(idx, source)| LineReport {
+        line: idx + 1,
+        outcome: Policy::parse(source).map(|policy| {
+            let verdicts = (PartOne::check(&policy), PartTwo::check(&policy));
+            (policy, verdicts)
+        }),
+    })
+}
+
+pub struct LineReport<'a> {
+    pub line: usize,
+    pub outcome: Result<(Policy<'a>, (Verdict, Verdict))>,
+}
+impl Display for LineReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn verdict(v: &Verdict) -> String {
+            match v {
+                Ok(()) => "valid".to_owned(),
+                Err(reason) => format!("invalid ({})", reason),
+            }
+        }
+        match &self.outcome {
+            Ok((policy, (one, two))) => write!(
+                f,
+                "line {}: {} — part one: {}; part two: {}",
+                self.line,
+                policy,
+                verdict(one),
+                verdict(two)
+            ),
+            Err(e) => write!(f, "line {}: parse error: {}", self.line, e),
+        }
+    }
+}
+
 impl<'a> Policy<'a> {
     fn parse(s: &'a str) -> Result<Policy<'a>> {
         fn single<T>(mut iter: impl Iterator<Item = T>) -> Result<T> {
@@ -29,14 +69,19 @@ impl<'a> Policy<'a> {
             bail!("Iterator did not contain a single value")
         }
 
-        let colon_idx = s.find(':').ok_or(anyhow!("Invalid password rule"))?;
+        let colon_idx = s
+            .find(':')
+            .ok_or_else(|| anyhow!("Invalid password rule"))?;
         let hyphen_idx = s[..colon_idx]
             .find('-')
-            .ok_or(anyhow!("Invalid password rule"))?;
+            .ok_or_else(|| anyhow!("Invalid password rule"))?;
         let space_idx = hyphen_idx
             + s[hyphen_idx..colon_idx]
                 .find(' ')
-                .ok_or(anyhow!("Invalid password rule"))?;
+                .ok_or_else(|| anyhow!("Invalid password rule"))?;
+        if !s[colon_idx..].starts_with(": ") {
+            bail!("Expected ': ' before the password")
+        }
         Ok(Policy {
             source: s,
             lower: s[..hyphen_idx].parse()?,
@@ -50,40 +95,420 @@ impl<'a> Policy<'a> {
     }
 }
 
+impl Display for Policy<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}-{} {}: {}",
+            self.lower,
+            self.upper,
+            self.control,
+            self.password()
+        )
+    }
+}
+
+/// Why a password fails a policy, if it does.
+pub type Verdict = std::result::Result<(), String>;
+
 pub trait Validation {
-    fn is_valid(policy: &Policy) -> bool;
+    fn check(policy: &Policy) -> Verdict;
+    fn is_valid(policy: &Policy) -> bool {
+        Self::check(policy).is_ok()
+    }
 }
 pub struct PartOne {}
 pub struct PartTwo {}
 
 impl Validation for PartOne {
+    fn check(policy: &Policy) -> Verdict {
+        check_count(
+            policy.password(),
+            std::slice::from_ref(&policy.control),
+            policy.lower,
+            policy.upper,
+        )
+    }
     fn is_valid(policy: &Policy) -> bool {
-        let count = policy
-            .password()
-            .chars()
-            .filter(|c| c == &policy.control)
-            .count();
+        let count = count_of(policy.password(), std::slice::from_ref(&policy.control));
         count >= policy.lower && count <= policy.upper
     }
 }
 impl Validation for PartTwo {
+    fn check(policy: &Policy) -> Verdict {
+        check_positions(
+            policy.password(),
+            std::slice::from_ref(&policy.control),
+            policy.lower,
+            policy.upper,
+        )
+    }
     fn is_valid(policy: &Policy) -> bool {
-        (policy.control
-            == policy.password()[..=policy.upper - 1]
-                .chars()
-                .last()
-                .unwrap())
-            ^ (policy.control
-                == policy.password()[policy.lower - 1..]
-                    .chars()
-                    .next()
-                    .unwrap())
+        let password = policy.password();
+        match (
+            char_at(password, policy.lower),
+            char_at(password, policy.upper),
+        ) {
+            (Some(first), Some(second)) => (first == policy.control) != (second == policy.control),
+            _ => false,
+        }
+    }
+}
+
+fn count_of(password: &str, chars: &[char]) -> usize {
+    password.chars().filter(|c| chars.contains(c)).count()
+}
+/// The character at a position counted from 1, not a byte offset from 0.
+fn char_at(password: &str, position: usize) -> Option<char> {
+    position
+        .checked_sub(1)
+        .and_then(|idx| password.chars().nth(idx))
+}
+fn check_count(password: &str, chars: &[char], lower: usize, upper: usize) -> Verdict {
+    let count = count_of(password, chars);
+    if count >= lower && count <= upper {
+        Ok(())
+    } else {
+        Err(format!(
+            "found {} '{}', need {}–{}",
+            count,
+            chars.iter().collect::<String>(),
+            lower,
+            upper
+        ))
+    }
+}
+fn check_positions(password: &str, chars: &[char], first: usize, second: usize) -> Verdict {
+    let at = |position: usize| {
+        char_at(password, position).ok_or_else(|| {
+            format!(
+                "position {} is outside the {}-character password",
+                position,
+                password.chars().count()
+            )
+        })
+    };
+    let matches = (chars.contains(&at(first)?), chars.contains(&at(second)?));
+    match matches {
+        (true, true) => Err("both positions match".to_owned()),
+        (false, false) => Err("neither position matches".to_owned()),
+        _ => Ok(()),
+    }
+}
+
+/// A validation to count in [`evaluate_reader`].
+#[derive(Clone, Copy)]
+pub struct Registered {
+    pub name: &'static str,
+    pub is_valid: fn(&Policy) -> bool,
+}
+impl Registered {
+    pub fn new<T: Validation>(name: &'static str) -> Registered {
+        Registered {
+            name,
+            is_valid: T::is_valid,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Counts {
+    pub lines: usize,
+    pub malformed: usize,
+    /// How many policies passed each validation, in registration order.
+    pub valid: Vec<usize>,
+}
+
+/// Counts the policies passing every validation in a single pass over `reader`, reusing one
+/// line buffer so that inputs of any size run in constant memory. Lines which are not UTF-8
+/// count as malformed.
+pub fn evaluate_reader(mut reader: impl BufRead, validations: &[Registered]) -> Result<Counts> {
+    let mut counts = Counts {
+        lines: 0,
+        malformed: 0,
+        valid: vec![0; validations.len()],
+    };
+    let mut buffer = Vec::new();
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer)? == 0 {
+            return Ok(counts);
+        }
+        counts.lines += 1;
+        let line = match std::str::from_utf8(&buffer) {
+            Ok(line) => line.trim_end_matches(&['\n', '\r'][..]),
+            Err(_) => {
+                counts.malformed += 1;
+                continue;
+            }
+        };
+        match Policy::parse(line) {
+            Ok(policy) => {
+                for (count, validation) in counts.valid.iter_mut().zip(validations) {
+                    if (validation.is_valid)(&policy) {
+                        *count += 1;
+                    }
+                }
+            }
+            Err(_) => counts.malformed += 1,
+        }
+    }
+}
+
+/// Counts the lines of `s` whose password satisfies the rule in front of it.
+pub fn evaluate_rules(s: &str) -> Result<usize> {
+    let mut count = 0;
+    for line in s.lines() {
+        if RuleLine::parse(line)?.check().is_ok() {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// A kind of character that a password can be required to contain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Digit,
+    Lower,
+    Upper,
+    Alpha,
+    Alphanumeric,
+    Symbol,
+}
+impl CharClass {
+    fn parse(s: &str) -> Result<CharClass> {
+        Ok(match s {
+            "digit" => CharClass::Digit,
+            "lower" => CharClass::Lower,
+            "upper" => CharClass::Upper,
+            "alpha" => CharClass::Alpha,
+            "alnum" => CharClass::Alphanumeric,
+            "symbol" => CharClass::Symbol,
+            _ => bail!("Unknown character class {}", s),
+        })
+    }
+    fn name(self) -> &'static str {
+        match self {
+            CharClass::Digit => "digit",
+            CharClass::Lower => "lower",
+            CharClass::Upper => "upper",
+            CharClass::Alpha => "alpha",
+            CharClass::Alphanumeric => "alnum",
+            CharClass::Symbol => "symbol",
+        }
+    }
+    fn contains(self, c: char) -> bool {
+        match self {
+            CharClass::Digit => c.is_numeric(),
+            CharClass::Lower => c.is_lowercase(),
+            CharClass::Upper => c.is_uppercase(),
+            CharClass::Alpha => c.is_alphabetic(),
+            CharClass::Alphanumeric => c.is_alphanumeric(),
+            CharClass::Symbol => !c.is_alphanumeric() && !c.is_whitespace(),
+        }
+    }
+}
+
+/// A password rule in the extended policy language.
+///
+/// Rules are written in front of the password as `<rule>: <password>`, where a rule is one of
+///
+/// * `sled 1-3 a` (or just `1-3 a`): the first puzzle's policy
+/// * `toboggan 1-3 a`: the second puzzle's policy
+/// * `count 1-3 abc`: between 1 and 3 characters from `abc`
+/// * `pos 1-3 abc`: exactly one of positions 1 and 3 holds a character from `abc`
+/// * `none abc`: no characters from `abc`
+/// * `class digit`: at least one `digit`, `lower`, `upper`, `alpha`, `alnum` or `symbol`
+/// * `len 8-64`: between 8 and 64 characters long
+///
+/// combined with `&`, `|` and parentheses, where `&` binds tighter than `|`. Every token,
+/// including operators and parentheses, is separated by whitespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    Count {
+        chars: Vec<char>,
+        lower: usize,
+        upper: usize,
+    },
+    Positions {
+        chars: Vec<char>,
+        first: usize,
+        second: usize,
+    },
+    Exclude(Vec<char>),
+    Class(CharClass),
+    Length {
+        lower: usize,
+        upper: usize,
+    },
+    All(Vec<Rule>),
+    Any(Vec<Rule>),
+}
+
+impl Rule {
+    pub fn parse(s: &str) -> Result<Rule> {
+        let mut tokens = s.split_whitespace().peekable();
+        let rule = Rule::parse_any(&mut tokens)?;
+        if let Some(token) = tokens.next() {
+            bail!("Unexpected {} in rule", token)
+        }
+        Ok(rule)
+    }
+    fn parse_any<'a>(tokens: &mut Peekable<impl Iterator<Item = &'a str>>) -> Result<Rule> {
+        let mut rules = vec![Rule::parse_all(tokens)?];
+        while tokens.next_if_eq(&"|").is_some() {
+            rules.push(Rule::parse_all(tokens)?);
+        }
+        Ok(Rule::combine(rules, Rule::Any))
+    }
+    fn parse_all<'a>(tokens: &mut Peekable<impl Iterator<Item = &'a str>>) -> Result<Rule> {
+        let mut rules = vec![Rule::parse_single(tokens)?];
+        while tokens.next_if_eq(&"&").is_some() {
+            rules.push(Rule::parse_single(tokens)?);
+        }
+        Ok(Rule::combine(rules, Rule::All))
+    }
+    fn parse_single<'a>(tokens: &mut Peekable<impl Iterator<Item = &'a str>>) -> Result<Rule> {
+        fn operand<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Result<&'a str> {
+            tokens.next().ok_or_else(|| anyhow!("Rule ended early"))
+        }
+        let keyword = operand(tokens)?;
+        Ok(match keyword {
+            "(" => {
+                let rule = Rule::parse_any(tokens)?;
+                if tokens.next() != Some(")") {
+                    bail!("Expected )")
+                }
+                rule
+            }
+            "sled" | "toboggan" | "count" | "pos" => {
+                let (lower, upper) = parse_range(operand(tokens)?)?;
+                let chars = operand(tokens)?.chars().collect::<Vec<_>>();
+                if matches!(keyword, "sled" | "toboggan") && chars.len() != 1 {
+                    bail!("{} takes a single character", keyword)
+                }
+                if matches!(keyword, "sled" | "count") {
+                    Rule::Count {
+                        chars,
+                        lower,
+                        upper,
+                    }
+                } else {
+                    Rule::Positions {
+                        chars,
+                        first: lower,
+                        second: upper,
+                    }
+                }
+            }
+            "none" => Rule::Exclude(operand(tokens)?.chars().collect()),
+            "class" => Rule::Class(CharClass::parse(operand(tokens)?)?),
+            "len" => {
+                let (lower, upper) = parse_range(operand(tokens)?)?;
+                Rule::Length { lower, upper }
+            }
+            // the original `1-3 a` syntax
+            range if range.contains('-') => {
+                let (lower, upper) = parse_range(range)?;
+                let chars = operand(tokens)?.chars().collect::<Vec<_>>();
+                if chars.len() != 1 {
+                    bail!("Expected a single character after {}", range)
+                }
+                Rule::Count {
+                    chars,
+                    lower,
+                    upper,
+                }
+            }
+            _ => bail!("Unknown rule {}", keyword),
+        })
+    }
+    fn combine(mut rules: Vec<Rule>, wrap: fn(Vec<Rule>) -> Rule) -> Rule {
+        if rules.len() == 1 {
+            rules.pop().unwrap()
+        } else {
+            wrap(rules)
+        }
+    }
+
+    pub fn check(&self, password: &str) -> Verdict {
+        match self {
+            Rule::Count {
+                chars,
+                lower,
+                upper,
+            } => check_count(password, chars, *lower, *upper),
+            Rule::Positions {
+                chars,
+                first,
+                second,
+            } => check_positions(password, chars, *first, *second),
+            Rule::Exclude(chars) => match password.chars().find(|c| chars.contains(c)) {
+                Some(c) => Err(format!("contains '{}'", c)),
+                None => Ok(()),
+            },
+            Rule::Class(class) => {
+                if password.chars().any(|c| class.contains(c)) {
+                    Ok(())
+                } else {
+                    Err(format!("no {} character", class.name()))
+                }
+            }
+            Rule::Length { lower, upper } => {
+                let len = password.chars().count();
+                if len >= *lower && len <= *upper {
+                    Ok(())
+                } else {
+                    Err(format!("length {}, need {}–{}", len, lower, upper))
+                }
+            }
+            Rule::All(rules) => rules.iter().try_for_each(|rule| rule.check(password)),
+            Rule::Any(rules) => {
+                let mut reasons = Vec::new();
+                for rule in rules {
+                    match rule.check(password) {
+                        Ok(()) => return Ok(()),
+                        Err(reason) => reasons.push(reason),
+                    }
+                }
+                Err(reasons.join(" and "))
+            }
+        }
+    }
+}
+
+fn parse_range(s: &str) -> Result<(usize, usize)> {
+    let (lower, upper) = split_tuple_2(s, "-").ok_or_else(|| anyhow!("Invalid range {}", s))?;
+    Ok((lower.parse()?, upper.parse()?))
+}
+
+/// A password database line in the extended policy language.
+#[derive(Debug, PartialEq)]
+pub struct RuleLine<'a> {
+    pub rule: Rule,
+    pub password: &'a str,
+}
+impl<'a> RuleLine<'a> {
+    pub fn parse(s: &'a str) -> Result<RuleLine<'a>> {
+        let (rule, password) =
+            split_tuple_2(s, ": ").ok_or_else(|| anyhow!("Expected ': ' before the password"))?;
+        Ok(RuleLine {
+            rule: Rule::parse(rule)?,
+            password,
+        })
+    }
+    pub fn check(&self) -> Verdict {
+        self.rule.check(self.password)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{evaluate, PartOne, PartTwo, Policy, Validation};
+    use super::{
+        evaluate, evaluate_reader, evaluate_rules, report, CharClass, Counts, PartOne, PartTwo,
+        Policy, Registered, Rule, RuleLine, Validation,
+    };
     const GOOD_POLICY: &str = "1-3 a: aaa";
     const EXAMPLE: &str = "1-3 a: abcde
 1-3 b: cdefg
@@ -146,4 +571,163 @@ mod tests {
             .unwrap();
         assert_eq!(vec![true, false, false], results)
     }
+
+    #[test]
+    fn explains_failures() {
+        assert_eq!(
+            Err("found 0 'b', need 1–3".to_owned()),
+            PartOne::check(&Policy::parse("1-3 b: cdefg").unwrap())
+        );
+        assert_eq!(
+            Err("both positions match".to_owned()),
+            PartTwo::check(&Policy::parse("1-3 a: aba").unwrap())
+        );
+        assert_eq!(
+            Err("neither position matches".to_owned()),
+            PartTwo::check(&Policy::parse("1-3 b: cdefg").unwrap())
+        );
+    }
+    #[test]
+    fn reports_every_line() {
+        let lines = report("1-3 a: abcde\n1-3 b: cdefg\nnonsense\n2-9 c: ccccccccc")
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "line 1: 1-3 a: abcde — part one: valid; part two: valid",
+                "line 2: 1-3 b: cdefg — part one: invalid (found 0 'b', need 1–3); part two: invalid (neither position matches)",
+                "line 3: parse error: Invalid password rule",
+                "line 4: 2-9 c: ccccccccc — part one: valid; part two: invalid (both positions match)",
+            ],
+            lines
+        );
+    }
+    #[test]
+    fn positions_count_characters() {
+        assert!(PartTwo::is_valid(&Policy::parse("1-2 a: éa").unwrap()));
+        assert!(PartTwo::is_valid(&Policy::parse("2-3 é: aéa").unwrap()));
+        assert!(!PartTwo::is_valid(&Policy::parse("1-2 é: éé").unwrap()));
+        assert!(PartOne::is_valid(&Policy::parse("2-2 é: éaé").unwrap()));
+    }
+    #[test]
+    fn out_of_range_positions_are_invalid() {
+        assert_eq!(
+            Err("position 5 is outside the 3-character password".to_owned()),
+            PartTwo::check(&Policy::parse("1-5 a: abc").unwrap())
+        );
+        assert_eq!(
+            Err("position 0 is outside the 3-character password".to_owned()),
+            PartTwo::check(&Policy::parse("0-1 a: abc").unwrap())
+        );
+        assert!(!PartTwo::is_valid(&Policy::parse("1-2 a: ").unwrap()));
+    }
+    #[test]
+    fn rejects_missing_password() {
+        assert!(Policy::parse("1-3 a:").is_err());
+        assert!(Policy::parse("1-3 a:abc").is_err());
+    }
+    #[test]
+    fn named_rules_match_puzzle_rules() {
+        let sled = EXAMPLE.replace("1-", "sled 1-").replace("2-", "sled 2-");
+        let toboggan = EXAMPLE
+            .replace("1-", "toboggan 1-")
+            .replace("2-", "toboggan 2-");
+        assert_eq!(2, evaluate_rules(EXAMPLE).unwrap());
+        assert_eq!(2, evaluate_rules(&sled).unwrap());
+        assert_eq!(1, evaluate_rules(&toboggan).unwrap());
+    }
+    #[test]
+    fn parses_rules() {
+        assert_eq!(
+            Rule::Any(vec![
+                Rule::All(vec![
+                    Rule::Count {
+                        chars: vec!['a', 'b'],
+                        lower: 1,
+                        upper: 3
+                    },
+                    Rule::Exclude(vec!['x']),
+                ]),
+                Rule::All(vec![
+                    Rule::Class(CharClass::Digit),
+                    Rule::Any(vec![
+                        Rule::Length { lower: 8, upper: 9 },
+                        Rule::Positions {
+                            chars: vec!['z'],
+                            first: 1,
+                            second: 2
+                        },
+                    ]),
+                ]),
+            ]),
+            Rule::parse("count 1-3 ab & none x | class digit & ( len 8-9 | toboggan 1-2 z )")
+                .unwrap()
+        );
+        assert!(Rule::parse("sled 1-3 ab").is_err());
+        assert!(Rule::parse("class vowel").is_err());
+        assert!(Rule::parse("( len 1-2").is_err());
+        assert!(Rule::parse("len 1-2 )").is_err());
+        assert!(Rule::parse("len 1-2 &").is_err());
+        assert!(Rule::parse("len 1").is_err());
+    }
+    #[test]
+    fn checks_rules() {
+        let check = |s| RuleLine::parse(s).unwrap().check();
+        assert_eq!(Ok(()), check("count 2-2 ab: abc"));
+        assert_eq!(Ok(()), check("pos 1-3 xy: yab"));
+        assert_eq!(Err("contains 'q'".to_owned()), check("none pq: aqp"));
+        assert_eq!(Ok(()), check("class upper: abÉ"));
+        assert_eq!(
+            Err("no symbol character".to_owned()),
+            check("class symbol: ab1")
+        );
+        assert_eq!(Err("length 3, need 4–8".to_owned()), check("len 4-8: ééé"));
+        assert_eq!(Ok(()), check("len 1-3 & class digit: a1"));
+        assert_eq!(
+            Err("length 2, need 3–4 and found 0 'z', need 1–1".to_owned()),
+            check("len 3-4 | count 1-1 z: a1")
+        );
+    }
+    #[test]
+    fn evaluates_reader_in_one_pass() {
+        let input = "1-3 a: abcde\r\n1-3 b: cdefg\nnonsense\n\n2-9 c: ccccccccc\n";
+        let validations = [
+            Registered::new::<PartOne>("part one"),
+            Registered::new::<PartTwo>("part two"),
+        ];
+        assert_eq!(
+            Counts {
+                lines: 5,
+                malformed: 2,
+                valid: vec![2, 1]
+            },
+            evaluate_reader(input.as_bytes(), &validations).unwrap()
+        );
+    }
+    #[test]
+    fn counts_invalid_utf8_as_malformed() {
+        let input = b"1-3 a: abc\n1-3 a: \xff\xfe\n1-3 \xc3: a\n1-3 a: aaa";
+        assert_eq!(
+            Counts {
+                lines: 4,
+                malformed: 2,
+                valid: vec![2]
+            },
+            evaluate_reader(&input[..], &[Registered::new::<PartOne>("part one")]).unwrap()
+        );
+    }
+    #[test]
+    fn fast_validity_agrees_with_check() {
+        for line in &[
+            "1-2 a: éa",
+            "1-5 a: abc",
+            "0-1 a: abc",
+            "1-3 a: aba",
+            "2-2 é: éaé",
+        ] {
+            let policy = Policy::parse(line).unwrap();
+            assert_eq!(PartOne::check(&policy).is_ok(), PartOne::is_valid(&policy));
+            assert_eq!(PartTwo::check(&policy).is_ok(), PartTwo::is_valid(&policy));
+        }
+    }
 }
@@ -0,0 +1,567 @@
+use crate::util::{
+    grid::{Grid, Orientation},
+    split_tuple_2,
+};
+use anyhow::{anyhow, bail, ensure, Error, Result};
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt::Display,
+    str::FromStr,
+};
+
+pub fn part1(s: &str) -> u64 {
+    let tiles = parse(s).unwrap();
+    EdgeIndex::new(&tiles).corners(&tiles).iter().product()
+}
+pub fn part2(s: &str) -> usize {
+    let tiles = parse(s).unwrap();
+    let image = Arrangement::assemble(&tiles).unwrap().stitch();
+    roughness(&image).unwrap()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pixel {
+    Off,
+    On,
+}
+impl TryFrom<u8> for Pixel {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            b'.' => Pixel::Off,
+            b'#' => Pixel::On,
+            _ => bail!("Unexpected token {}", value),
+        })
+    }
+}
+impl Display for Pixel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Pixel::Off => '.',
+                Pixel::On => '#',
+            }
+        )
+    }
+}
+
+struct Tile {
+    id: u64,
+    image: Grid<Pixel>,
+}
+impl FromStr for Tile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (header, body) =
+            split_tuple_2(s.trim(), "\n").ok_or_else(|| anyhow!("Tile has no image"))?;
+        let id = header
+            .strip_prefix("Tile ")
+            .and_then(|h| h.strip_suffix(':'))
+            .ok_or_else(|| anyhow!("Bad tile header {}", header))?
+            .parse()?;
+        let image = Grid::parse(body)?;
+        ensure!(
+            image.width() == image.height(),
+            "Tile {} is not a square",
+            id
+        );
+        // edge signatures are packed into a u16
+        ensure!(
+            (3..=16).contains(&image.width()),
+            "Tile {} has unsupported size {}",
+            id,
+            image.width()
+        );
+        Ok(Tile { id, image })
+    }
+}
+fn parse(s: &str) -> Result<Vec<Tile>> {
+    s.split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// The four edges of an image, each read left-to-right or top-to-bottom as a bitmask.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Edges {
+    top: u16,
+    right: u16,
+    bottom: u16,
+    left: u16,
+}
+impl Edges {
+    fn of(image: &Grid<Pixel>) -> Edges {
+        fn signature<'a>(pixels: impl Iterator<Item = &'a Pixel>) -> u16 {
+            pixels.fold(0, |sig, &p| (sig << 1) | (p == Pixel::On) as u16)
+        }
+        Edges {
+            top: signature(image.row(0).iter()),
+            right: signature(image.column(image.width() - 1)),
+            bottom: signature(image.row(image.height() - 1).iter()),
+            left: signature(image.column(0)),
+        }
+    }
+    fn all(&self) -> [u16; 4] {
+        [self.top, self.right, self.bottom, self.left]
+    }
+}
+/// An edge read in either direction has the same canonical signature.
+fn canonical(signature: u16, length: usize) -> u16 {
+    signature.min(signature.reverse_bits() >> (16 - length))
+}
+
+struct EdgeIndex(HashMap<u16, Vec<u64>>);
+impl EdgeIndex {
+    fn new(tiles: &[Tile]) -> EdgeIndex {
+        let mut index = HashMap::new();
+        for tile in tiles {
+            for &edge in Edges::of(&tile.image).all().iter() {
+                index
+                    .entry(canonical(edge, tile.image.width()))
+                    .or_insert_with(Vec::new)
+                    .push(tile.id);
+            }
+        }
+        EdgeIndex(index)
+    }
+    fn unmatched_edge_count(&self, tile: &Tile) -> usize {
+        Edges::of(&tile.image)
+            .all()
+            .iter()
+            .filter(|&&edge| self.0[&canonical(edge, tile.image.width())].len() == 1)
+            .count()
+    }
+    /// Corner tiles are the only ones with two edges that match no other tile.
+    fn corners(&self, tiles: &[Tile]) -> Vec<u64> {
+        tiles
+            .iter()
+            .filter(|t| self.unmatched_edge_count(t) == 2)
+            .map(|t| t.id)
+            .collect()
+    }
+}
+
+struct Candidate {
+    id: u64,
+    image: Grid<Pixel>,
+    edges: Edges,
+}
+
+/// Oriented tiles in row-major order, forming a square.
+struct Arrangement {
+    side: usize,
+    placed: Vec<Candidate>,
+}
+impl Arrangement {
+    fn assemble(tiles: &[Tile]) -> Result<Arrangement> {
+        ensure!(!tiles.is_empty(), "No tiles to assemble");
+        let side = (tiles.len() as f64).sqrt().round() as usize;
+        ensure!(
+            side * side == tiles.len(),
+            "{} tiles cannot make a square",
+            tiles.len()
+        );
+        let candidates = tiles
+            .iter()
+            .flat_map(|t| {
+                Orientation::all().map(move |o| {
+                    let image = t.image.oriented(o);
+                    Candidate {
+                        id: t.id,
+                        edges: Edges::of(&image),
+                        image,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut by_left: HashMap<u16, Vec<usize>> = HashMap::new();
+        let mut by_top: HashMap<u16, Vec<usize>> = HashMap::new();
+        for (idx, c) in candidates.iter().enumerate() {
+            by_left.entry(c.edges.left).or_default().push(idx);
+            by_top.entry(c.edges.top).or_default().push(idx);
+        }
+
+        struct Search<'a> {
+            side: usize,
+            candidates: &'a [Candidate],
+            by_left: HashMap<u16, Vec<usize>>,
+            by_top: HashMap<u16, Vec<usize>>,
+            used: HashSet<u64>,
+            placed: Vec<usize>,
+        }
+        impl Search<'_> {
+            fn place(&mut self) -> bool {
+                let pos = self.placed.len();
+                if pos == self.side * self.side {
+                    return true;
+                }
+                let above = if pos >= self.side {
+                    Some(self.candidates[self.placed[pos - self.side]].edges.bottom)
+                } else {
+                    None
+                };
+                let options = if !pos.is_multiple_of(self.side) {
+                    let left = self.candidates[self.placed[pos - 1]].edges.right;
+                    self.by_left.get(&left).cloned().unwrap_or_default()
+                } else if let Some(above) = above {
+                    self.by_top.get(&above).cloned().unwrap_or_default()
+                } else {
+                    (0..self.candidates.len()).collect()
+                };
+                for idx in options {
+                    let c = &self.candidates[idx];
+                    if self.used.contains(&c.id) || above.is_some_and(|a| a != c.edges.top) {
+                        continue;
+                    }
+                    self.used.insert(c.id);
+                    self.placed.push(idx);
+                    if self.place() {
+                        return true;
+                    }
+                    self.placed.pop();
+                    self.used.remove(&c.id);
+                }
+                false
+            }
+        }
+
+        let mut search = Search {
+            side,
+            candidates: &candidates,
+            by_left,
+            by_top,
+            used: HashSet::new(),
+            placed: Vec::with_capacity(tiles.len()),
+        };
+        if !search.place() {
+            bail!("No arrangement of the tiles fits together")
+        }
+        let order = search.placed;
+        let mut candidates = candidates.into_iter().map(Some).collect::<Vec<_>>();
+        let placed = order
+            .iter()
+            .map(|&idx| candidates[idx].take().unwrap())
+            .collect();
+        Ok(Arrangement { side, placed })
+    }
+    fn corners(&self) -> [u64; 4] {
+        let last = self.side - 1;
+        [
+            self.placed[0].id,
+            self.placed[last].id,
+            self.placed[last * self.side].id,
+            self.placed[last * self.side + last].id,
+        ]
+    }
+    /// Strips the border from each tile and joins the insides into a single image.
+    fn stitch(&self) -> Grid<Pixel> {
+        let inner = self.placed[0].image.width() - 2;
+        let mut output = Grid::filled(self.side * inner, self.side * inner, Pixel::Off);
+        for (idx, c) in self.placed.iter().enumerate() {
+            let (tile_x, tile_y) = (idx % self.side, idx / self.side);
+            let stripped = c.image.sub_grid(1, 1, inner, inner);
+            for (x, y) in stripped.positions() {
+                output[(tile_x * inner + x, tile_y * inner + y)] = stripped[(x, y)];
+            }
+        }
+        output
+    }
+}
+
+const SEA_MONSTER: [&str; 3] = [
+    "                  # ",
+    "#    ##    ##    ###",
+    " #  #  #  #  #  #   ",
+];
+
+fn pattern_offsets(pattern: &[&str]) -> Vec<(usize, usize)> {
+    pattern
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.bytes()
+                .enumerate()
+                .filter(|(_, b)| *b == b'#')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect()
+}
+/// Finds every top-left position at which all of the offsets land on an `On` pixel.
+fn find_pattern(image: &Grid<Pixel>, offsets: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    image
+        .positions()
+        .filter(|&(x, y)| {
+            offsets
+                .iter()
+                .all(|&(dx, dy)| image.get(x + dx, y + dy) == Some(&Pixel::On))
+        })
+        .collect()
+}
+/// Counts the `On` pixels which are not part of any sea monster, in whichever orientation of the
+/// image contains sea monsters.
+fn roughness(image: &Grid<Pixel>) -> Result<usize> {
+    let offsets = pattern_offsets(&SEA_MONSTER);
+    for orientation in Orientation::all() {
+        let oriented = image.oriented(orientation);
+        let found = find_pattern(&oriented, &offsets);
+        if !found.is_empty() {
+            let covered = found
+                .iter()
+                .flat_map(|&(x, y)| offsets.iter().map(move |&(dx, dy)| (x + dx, y + dy)))
+                .collect::<HashSet<_>>();
+            let total = oriented.iter().filter(|&&p| p == Pixel::On).count();
+            return Ok(total - covered.len());
+        }
+    }
+    bail!("No sea monsters in any orientation")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Tile 2311:
+..##.#..#.
+##..#.....
+#...##..#.
+####.#...#
+##.##.###.
+##...#.###
+.#.#.#..##
+..#....#..
+###...#.#.
+..###..###
+
+Tile 1951:
+#.##...##.
+#.####...#
+.....#..##
+#...######
+.##.#....#
+.###.#####
+###.##.##.
+.###....#.
+..#.#..#.#
+#...##.#..
+
+Tile 1171:
+####...##.
+#..##.#..#
+##.#..#.#.
+.###.####.
+..###.####
+.##....##.
+.#...####.
+#.##.####.
+####..#...
+.....##...
+
+Tile 1427:
+###.##.#..
+.#..#.##..
+.#.##.#..#
+#.#.#.##.#
+....#...##
+...##..##.
+...#.#####
+.#.####.#.
+..#..###.#
+..##.#..#.
+
+Tile 1489:
+##.#.#....
+..##...#..
+.##..##...
+..#...#...
+#####...#.
+#..#.#.#.#
+...#.#.#..
+##.#...##.
+..##.##.##
+###.##.#..
+
+Tile 2473:
+#....####.
+#..#.##...
+#.##..#...
+######.#.#
+.#...#.#.#
+.#########
+.###.#..#.
+########.#
+##...##.#.
+..###.#.#.
+
+Tile 2971:
+..#.#....#
+#...###...
+#.#.###...
+##.##..#..
+.#####..##
+.#..####.#
+#..#.#..#.
+..####.###
+..#.#.###.
+...#.#.#.#
+
+Tile 2729:
+...#.#.#.#
+####.#....
+..#.#.....
+....#..#.#
+.##..##.#.
+.#.####...
+####.#.#..
+##.####...
+##..#.##..
+#.##...##.
+
+Tile 3079:
+#.#.#####.
+.#..######
+..#.......
+######....
+####.#..#.
+.#...#.##.
+#.#####.##
+..#.###...
+..#.......
+..#.###...";
+
+    /// Cuts a pseudo-random picture into overlapping tiles, then scrambles their orientations.
+    fn make_puzzle(side: usize) -> (String, Grid<Pixel>, Vec<u64>) {
+        // wider than the puzzle's tiles, so that random edges are unlikely to collide
+        const TILE: usize = 16;
+        let mut seed = 20201220u64;
+        let mut next = || {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            if seed >> 63 == 1 {
+                Pixel::On
+            } else {
+                Pixel::Off
+            }
+        };
+        let size = side * (TILE - 1) + 1;
+        let cells = (0..size * size).map(|_| next()).collect();
+        let picture = Grid::from_cells(size, size, cells).unwrap();
+        let mut text = String::new();
+        let mut ids = Vec::new();
+        let orientations = Orientation::all().collect::<Vec<_>>();
+        for idx in 0..side * side {
+            let (tile_x, tile_y) = (idx % side, idx / side);
+            let orientation = orientations[idx % orientations.len()];
+            let id = 1000 + 7 * idx as u64;
+            let tile = picture
+                .sub_grid(tile_x * (TILE - 1), tile_y * (TILE - 1), TILE, TILE)
+                .oriented(orientation);
+            text.push_str(&format!("Tile {}:\n{}\n\n", id, tile));
+            ids.push(id);
+        }
+        let mut expected = Grid::filled(side * (TILE - 2), side * (TILE - 2), Pixel::Off);
+        for (x, y) in expected.positions().collect::<Vec<_>>() {
+            let (tile_x, tile_y) = (x / (TILE - 2), y / (TILE - 2));
+            expected[(x, y)] = picture[(
+                tile_x * (TILE - 1) + 1 + x % (TILE - 2),
+                tile_y * (TILE - 1) + 1 + y % (TILE - 2),
+            )];
+        }
+        (text, expected, ids)
+    }
+
+    #[test]
+    fn gets_example_1() {
+        assert_eq!(20899048083289, part1(EXAMPLE));
+    }
+    #[test]
+    fn gets_example_2() {
+        assert_eq!(273, part2(EXAMPLE));
+    }
+    #[test]
+    fn assembles_example() {
+        let tiles = parse(EXAMPLE).unwrap();
+        let mut corners = Arrangement::assemble(&tiles).unwrap().corners();
+        corners.sort_unstable();
+        assert_eq!([1171, 1951, 2971, 3079], corners);
+    }
+    #[test]
+    fn can_parse_tile() {
+        let tile: Tile = "Tile 1234:\n#..\n.#.\n..#".parse().unwrap();
+        assert_eq!(1234, tile.id);
+        assert_eq!(Pixel::On, tile.image[(1, 1)]);
+        assert_eq!(
+            Edges {
+                top: 0b100,
+                right: 0b001,
+                bottom: 0b001,
+                left: 0b100
+            },
+            Edges::of(&tile.image)
+        );
+    }
+    #[test]
+    fn rejects_bad_tile() {
+        assert!("Tile x:\n#.\n.#".parse::<Tile>().is_err());
+        assert!("Tile 1:\n#..\n.#.".parse::<Tile>().is_err());
+        assert!("Tile 1:\n#x.\n.#.\n...".parse::<Tile>().is_err());
+    }
+    #[test]
+    fn canonical_edge_ignores_direction() {
+        assert_eq!(canonical(0b1100000000, 10), canonical(0b0000000011, 10));
+        assert_eq!(0b0000000011, canonical(0b1100000000, 10));
+    }
+    #[test]
+    fn finds_corners_from_edges() {
+        let (text, _, ids) = make_puzzle(3);
+        let tiles = parse(&text).unwrap();
+        let mut corners = EdgeIndex::new(&tiles).corners(&tiles);
+        corners.sort_unstable();
+        assert_eq!(vec![ids[0], ids[2], ids[6], ids[8]], corners);
+        assert_eq!(ids[0] * ids[2] * ids[6] * ids[8], part1(&text));
+    }
+    #[test]
+    fn assembles_scrambled_tiles() {
+        let (text, expected, ids) = make_puzzle(3);
+        let tiles = parse(&text).unwrap();
+        let arrangement = Arrangement::assemble(&tiles).unwrap();
+        let mut corners = arrangement.corners();
+        corners.sort_unstable();
+        assert_eq!([ids[0], ids[2], ids[6], ids[8]], corners);
+        let image = arrangement.stitch();
+        assert!(Orientation::all().any(|o| image.oriented(o) == expected));
+    }
+    #[test]
+    fn assembly_needs_a_square() {
+        let (text, _, _) = make_puzzle(2);
+        let tiles = parse(&text).unwrap();
+        assert!(Arrangement::assemble(&tiles[..3]).is_err());
+        assert!(Arrangement::assemble(&[]).is_err());
+    }
+    #[test]
+    fn finds_sea_monsters_in_any_orientation() {
+        let image = Grid::<Pixel>::parse(
+            "..................#.#
+#....##....##....###.
+.#..#..#..#..#..#....
+#....................",
+        )
+        .unwrap();
+        let offsets = pattern_offsets(&SEA_MONSTER);
+        assert_eq!(15, offsets.len());
+        assert_eq!(vec![(0, 0)], find_pattern(&image, &offsets));
+        for orientation in Orientation::all() {
+            assert_eq!(2, roughness(&image.oriented(orientation)).unwrap());
+        }
+    }
+    #[test]
+    fn no_sea_monsters_is_an_error() {
+        assert!(roughness(&Grid::parse("##\n##").unwrap()).is_err());
+    }
+}
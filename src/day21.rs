@@ -0,0 +1,116 @@
+use crate::util::{flat_dedup, split_tuple_2};
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, HashSet};
+
+pub fn part1(s: &str) -> usize {
+    let foods = parse(s).unwrap();
+    let suspects = candidates(&foods)
+        .into_values()
+        .flatten()
+        .collect::<HashSet<_>>();
+    foods
+        .iter()
+        .flat_map(|f| f.ingredients.iter())
+        .filter(|i| !suspects.contains(*i))
+        .count()
+}
+pub fn part2(s: &str) -> String {
+    let foods = parse(s).unwrap();
+    resolve(&foods)
+        .unwrap()
+        .into_iter()
+        .map(|(_, ingredient)| ingredient)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[derive(Debug, PartialEq)]
+struct Food<'a> {
+    ingredients: Vec<&'a str>,
+    allergens: Vec<&'a str>,
+}
+impl<'a> Food<'a> {
+    fn parse(s: &'a str) -> Result<Food<'a>> {
+        let (ingredients, allergens) = if let Some((lhs, rhs)) = split_tuple_2(s, " (contains ") {
+            let rhs = rhs
+                .strip_suffix(')')
+                .ok_or_else(|| anyhow!("Unterminated allergen list in {}", s))?;
+            (lhs, rhs.split(", ").collect())
+        } else {
+            (s, Vec::new())
+        };
+        Ok(Food {
+            ingredients: ingredients.split_whitespace().collect(),
+            allergens,
+        })
+    }
+}
+fn parse(s: &str) -> Result<Vec<Food<'_>>> {
+    s.lines().map(Food::parse).collect()
+}
+
+/// Each allergen must be in one of the ingredients common to every food which lists it.
+fn candidates<'a>(foods: &[Food<'a>]) -> BTreeMap<&'a str, Vec<&'a str>> {
+    let mut output: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for food in foods {
+        for &allergen in &food.allergens {
+            if let Some(existing) = output.get_mut(allergen) {
+                existing.retain(|i| food.ingredients.contains(i));
+            } else {
+                output.insert(allergen, food.ingredients.clone());
+            }
+        }
+    }
+    output
+}
+/// Pairs each allergen, in alphabetical order, with the single ingredient that contains it.
+fn resolve<'a>(foods: &[Food<'a>]) -> Result<Vec<(&'a str, &'a str)>> {
+    let (allergens, options): (Vec<_>, Vec<_>) = candidates(foods).into_iter().unzip();
+    let ingredients = flat_dedup::<str>(options)?;
+    Ok(allergens.into_iter().zip(ingredients).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "mxmxvkd kfcds sqjhc nhms (contains dairy, fish)
+trh fvjkl sbzzf mxmxvkd (contains dairy)
+sqjhc fvjkl (contains soy)
+sqjhc mxmxvkd sbzzf (contains fish)";
+
+    #[test]
+    fn can_parse() {
+        assert_eq!(
+            Food {
+                ingredients: vec!["sqjhc", "fvjkl"],
+                allergens: vec!["soy"]
+            },
+            Food::parse("sqjhc fvjkl (contains soy)").unwrap()
+        );
+        assert_eq!(
+            Food {
+                ingredients: vec!["sqjhc"],
+                allergens: vec![]
+            },
+            Food::parse("sqjhc").unwrap()
+        );
+        assert!(Food::parse("sqjhc (contains soy").is_err());
+    }
+    #[test]
+    fn gets_candidates() {
+        let foods = parse(EXAMPLE).unwrap();
+        let c = candidates(&foods);
+        assert_eq!(vec!["mxmxvkd"], c["dairy"]);
+        assert_eq!(vec!["mxmxvkd", "sqjhc"], c["fish"]);
+        assert_eq!(vec!["sqjhc", "fvjkl"], c["soy"]);
+    }
+    #[test]
+    fn gets_example_1() {
+        assert_eq!(5, part1(EXAMPLE));
+    }
+    #[test]
+    fn gets_example_2() {
+        assert_eq!("mxmxvkd,sqjhc,fvjkl", part2(EXAMPLE));
+    }
+}
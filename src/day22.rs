@@ -0,0 +1,324 @@
+use crate::util::split_tuple_2;
+use anyhow::{anyhow, bail, Result};
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt::Display,
+};
+
+pub fn part1(s: &str) -> usize {
+    play(parse(s).unwrap(), Rules::Combat).unwrap().score
+}
+pub fn part2(s: &str) -> usize {
+    play(parse(s).unwrap(), Rules::Recursive).unwrap().score
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rules {
+    Combat,
+    Recursive,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Player {
+    One,
+    Two,
+}
+impl Display for Player {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Player::One => write!(f, "1"),
+            Player::Two => write!(f, "2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Deck(VecDeque<usize>);
+impl Deck {
+    fn parse(s: &str, player: Player) -> Result<Deck> {
+        let mut lines = s.trim().lines();
+        let header = format!("Player {}:", player);
+        if lines.next() != Some(header.as_str()) {
+            bail!("Expected deck to start with {}", header)
+        }
+        Ok(Deck(lines.map(str::parse).collect::<Result<_, _>>()?))
+    }
+    fn score(&self) -> usize {
+        self.0
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(idx, card)| (idx + 1) * card)
+            .sum()
+    }
+    /// Copies the top `count` cards into a new deck, for a sub-game.
+    fn take(&self, count: usize) -> Deck {
+        Deck(self.0.iter().take(count).copied().collect())
+    }
+}
+impl Display for Deck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, card) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", card)?;
+        }
+        Ok(())
+    }
+}
+pub fn parse(s: &str) -> Result<(Deck, Deck)> {
+    let (one, two) = split_tuple_2(s, "\n\n").ok_or_else(|| anyhow!("Expected two decks"))?;
+    Ok((
+        Deck::parse(one, Player::One)?,
+        Deck::parse(two, Player::Two)?,
+    ))
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Outcome {
+    pub winner: Player,
+    pub deck: Deck,
+    pub score: usize,
+}
+
+/// Something that happened during a game, for following along with the puzzle text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Round {
+        game: usize,
+        round: usize,
+        decks: (Deck, Deck),
+    },
+    SubGame,
+    RoundWon {
+        game: usize,
+        round: usize,
+        winner: Player,
+    },
+    RepeatedState {
+        game: usize,
+        round: usize,
+    },
+    GameWon {
+        game: usize,
+        winner: Player,
+    },
+}
+impl Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::Round { game, round, decks } => write!(
+                f,
+                "-- Round {} (Game {}) --\nPlayer 1's deck: {}\nPlayer 2's deck: {}\nPlayer 1 plays: {}\nPlayer 2 plays: {}",
+                round,
+                game,
+                decks.0,
+                decks.1,
+                decks.0 .0[0],
+                decks.1 .0[0]
+            ),
+            Event::SubGame => write!(f, "Playing a sub-game to determine the winner..."),
+            Event::RoundWon {
+                game,
+                round,
+                winner,
+            } => write!(f, "Player {} wins round {} of game {}!", winner, round, game),
+            Event::RepeatedState { game, round } => write!(
+                f,
+                "Round {} of game {} repeats an earlier state; player 1 wins",
+                round, game
+            ),
+            Event::GameWon { game, winner } => {
+                write!(f, "The winner of game {} is player {}!", game, winner)
+            }
+        }
+    }
+}
+
+struct Game {
+    rules: Rules,
+    game_count: usize,
+    log: Option<Vec<Event>>,
+}
+impl Game {
+    fn record(&mut self, event: impl FnOnce() -> Event) {
+        if let Some(log) = self.log.as_mut() {
+            log.push(event());
+        }
+    }
+    /// Plays to the end, failing if a game of plain Combat repeats itself and so would never end.
+    fn play(&mut self, decks: &mut (Deck, Deck)) -> Result<Player> {
+        self.game_count += 1;
+        let game = self.game_count;
+        let mut seen = HashSet::new();
+        let mut round = 0;
+        let winner = loop {
+            if decks.0 .0.is_empty() {
+                break Player::Two;
+            }
+            if decks.1 .0.is_empty() {
+                break Player::One;
+            }
+            round += 1;
+            if !seen.insert(decks.clone()) {
+                if self.rules == Rules::Combat {
+                    bail!(
+                        "Round {} repeats an earlier state, so the game never ends",
+                        round
+                    );
+                }
+                self.record(|| Event::RepeatedState { game, round });
+                break Player::One;
+            }
+            self.record(|| Event::Round {
+                game,
+                round,
+                decks: decks.clone(),
+            });
+            let one = decks.0 .0.pop_front().unwrap();
+            let two = decks.1 .0.pop_front().unwrap();
+            let round_winner = if self.rules == Rules::Recursive
+                && decks.0 .0.len() >= one
+                && decks.1 .0.len() >= two
+            {
+                self.record(|| Event::SubGame);
+                self.play(&mut (decks.0.take(one), decks.1.take(two)))?
+            } else if one > two {
+                Player::One
+            } else {
+                Player::Two
+            };
+            self.record(|| Event::RoundWon {
+                game,
+                round,
+                winner: round_winner,
+            });
+            match round_winner {
+                Player::One => decks.0 .0.extend([one, two].iter()),
+                Player::Two => decks.1 .0.extend([two, one].iter()),
+            }
+        };
+        self.record(|| Event::GameWon { game, winner });
+        Ok(winner)
+    }
+}
+
+fn play_with_log(
+    decks: (Deck, Deck),
+    rules: Rules,
+    log: Option<Vec<Event>>,
+) -> Result<(Outcome, Option<Vec<Event>>)> {
+    let mut game = Game {
+        rules,
+        game_count: 0,
+        log,
+    };
+    let mut decks = decks;
+    let winner = game.play(&mut decks)?;
+    let deck = match winner {
+        Player::One => decks.0,
+        Player::Two => decks.1,
+    };
+    let outcome = Outcome {
+        winner,
+        score: deck.score(),
+        deck,
+    };
+    Ok((outcome, game.log))
+}
+pub fn play(decks: (Deck, Deck), rules: Rules) -> Result<Outcome> {
+    Ok(play_with_log(decks, rules, None)?.0)
+}
+/// Plays the game while recording every step, which is slow for real inputs.
+pub fn play_logged(decks: (Deck, Deck), rules: Rules) -> Result<(Outcome, Vec<Event>)> {
+    let (outcome, log) = play_with_log(decks, rules, Some(Vec::new()))?;
+    Ok((outcome, log.unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Player 1:
+9
+2
+6
+3
+1
+
+Player 2:
+5
+8
+4
+7
+10";
+
+    fn deck(cards: &[usize]) -> Deck {
+        Deck(cards.iter().copied().collect())
+    }
+
+    #[test]
+    fn can_parse() {
+        let (one, two) = parse(EXAMPLE).unwrap();
+        assert_eq!(deck(&[9, 2, 6, 3, 1]), one);
+        assert_eq!(deck(&[5, 8, 4, 7, 10]), two);
+        assert!(parse("Player 2:\n1\n\nPlayer 1:\n2").is_err());
+    }
+    #[test]
+    fn plays_example_1() {
+        assert_eq!(
+            Outcome {
+                winner: Player::Two,
+                deck: deck(&[3, 2, 10, 6, 8, 5, 9, 4, 7, 1]),
+                score: 306
+            },
+            play(parse(EXAMPLE).unwrap(), Rules::Combat).unwrap()
+        );
+    }
+    #[test]
+    fn plays_example_2() {
+        assert_eq!(
+            Outcome {
+                winner: Player::Two,
+                deck: deck(&[7, 5, 6, 2, 4, 1, 10, 8, 9, 3]),
+                score: 291
+            },
+            play(parse(EXAMPLE).unwrap(), Rules::Recursive).unwrap()
+        );
+    }
+    #[test]
+    fn recursive_game_terminates() {
+        let decks = (deck(&[43, 19]), deck(&[2, 29, 14]));
+        assert_eq!(
+            Player::One,
+            play(decks.clone(), Rules::Recursive).unwrap().winner
+        );
+        assert!(play(decks, Rules::Combat).is_err());
+    }
+    #[test]
+    fn logs_example() {
+        let (outcome, log) = play_logged(parse(EXAMPLE).unwrap(), Rules::Recursive).unwrap();
+        assert_eq!(Player::Two, outcome.winner);
+        assert_eq!(
+            "-- Round 1 (Game 1) --
+Player 1's deck: 9, 2, 6, 3, 1
+Player 2's deck: 5, 8, 4, 7, 10
+Player 1 plays: 9
+Player 2 plays: 5
+Player 1 wins round 1 of game 1!",
+            log[..2]
+                .iter()
+                .map(Event::to_string)
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+        assert!(log.contains(&Event::SubGame));
+        assert_eq!(
+            Some(&Event::GameWon {
+                game: 1,
+                winner: Player::Two
+            }),
+            log.last()
+        );
+    }
+}
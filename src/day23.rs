@@ -0,0 +1,149 @@
+use anyhow::{anyhow, ensure, Result};
+
+pub fn part1(s: &str) -> String {
+    let mut cups = Cups::new(&parse(s).unwrap(), 9).unwrap();
+    cups.play(100);
+    cups.after_one().map(|c| c.to_string()).collect()
+}
+pub fn part2(s: &str) -> u64 {
+    let mut cups = Cups::new(&parse(s).unwrap(), 1_000_000).unwrap();
+    cups.play(10_000_000);
+    cups.after_one().take(2).map(u64::from).product()
+}
+
+pub fn parse(s: &str) -> Result<Vec<u32>> {
+    s.trim()
+        .chars()
+        .map(|c| c.to_digit(10).ok_or_else(|| anyhow!("Bad cup label {}", c)))
+        .collect()
+}
+
+/// A circle of cups labelled `1..=n`, stored as the label of the cup clockwise of each label.
+/// Index zero is unused.
+pub struct Cups {
+    next: Vec<u32>,
+    current: u32,
+}
+impl Cups {
+    /// Lays out the initial cups, then fills the circle up to `count` with ascending labels.
+    pub fn new(initial: &[u32], count: usize) -> Result<Cups> {
+        ensure!(!initial.is_empty(), "No cups");
+        // a move needs the current cup, three to pick up, and somewhere to put them
+        ensure!(
+            count >= initial.len().max(5) && count <= u32::MAX as usize,
+            "Cannot fit {} labelled cups into a circle of {}",
+            initial.len(),
+            count
+        );
+        let mut seen = vec![false; initial.len() + 1];
+        for &label in initial {
+            let cell = seen
+                .get_mut(label as usize)
+                .filter(|_| label > 0)
+                .ok_or_else(|| anyhow!("Cup {} out of range", label))?;
+            ensure!(!*cell, "Duplicate cup {}", label);
+            *cell = true;
+        }
+        let order = initial
+            .iter()
+            .copied()
+            .chain(initial.len() as u32 + 1..=count as u32);
+        let mut next = vec![0; count + 1];
+        let mut prev = None;
+        for label in order {
+            if let Some(prev) = prev {
+                next[prev as usize] = label;
+            }
+            prev = Some(label);
+        }
+        next[prev.unwrap() as usize] = initial[0];
+        Ok(Cups {
+            next,
+            current: initial[0],
+        })
+    }
+    fn max_label(&self) -> u32 {
+        self.next.len() as u32 - 1
+    }
+    fn step(&mut self) {
+        let first = self.next[self.current as usize];
+        let second = self.next[first as usize];
+        let third = self.next[second as usize];
+        let mut destination = self.current;
+        loop {
+            destination = if destination == 1 {
+                self.max_label()
+            } else {
+                destination - 1
+            };
+            if destination != first && destination != second && destination != third {
+                break;
+            }
+        }
+        // splice the picked-up run out after current, and back in after destination
+        self.next[self.current as usize] = self.next[third as usize];
+        self.next[third as usize] = self.next[destination as usize];
+        self.next[destination as usize] = first;
+        self.current = self.next[self.current as usize];
+    }
+    pub fn play(&mut self, moves: usize) {
+        for _ in 0..moves {
+            self.step();
+        }
+    }
+    /// Labels clockwise of cup 1, not including cup 1 itself.
+    pub fn after_one(&self) -> impl Iterator<Item = u32> + '_ {
+        std::iter::successors(Some(self.next[1]), move |&c| Some(self.next[c as usize]))
+            .take_while(|&c| c != 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "389125467";
+
+    fn labels(cups: &Cups) -> String {
+        cups.after_one().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn can_create() {
+        let cups = Cups::new(&parse(EXAMPLE).unwrap(), 9).unwrap();
+        assert_eq!("25467389", labels(&cups));
+        assert_eq!(3, cups.current);
+    }
+    #[test]
+    fn can_extend() {
+        let cups = Cups::new(&[2, 1], 5).unwrap();
+        assert_eq!("3452", labels(&cups));
+    }
+    #[test]
+    fn rejects_bad_labels() {
+        assert!(Cups::new(&[], 9).is_err());
+        assert!(Cups::new(&[1, 2, 2], 9).is_err());
+        assert!(Cups::new(&[1, 3], 9).is_err());
+        assert!(Cups::new(&[0, 1], 9).is_err());
+        assert!(Cups::new(&[1, 2, 3], 2).is_err());
+        assert!(Cups::new(&[1, 2, 3], 4).is_err());
+        assert!(parse("12a").is_err());
+    }
+    #[test]
+    fn can_step() {
+        let mut cups = Cups::new(&parse(EXAMPLE).unwrap(), 9).unwrap();
+        cups.step();
+        assert_eq!(2, cups.current);
+        assert_eq!("54673289", labels(&cups));
+        cups.play(9);
+        assert_eq!("92658374", labels(&cups));
+    }
+    #[test]
+    fn gets_example_1() {
+        assert_eq!("67384529", part1(EXAMPLE));
+    }
+    #[test]
+    fn gets_example_2() {
+        assert_eq!(149245887792, part2(EXAMPLE));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::util::automaton::Generations;
+use anyhow::{bail, Result};
+use std::collections::{HashMap, HashSet};
+
+pub fn part1(s: &str) -> usize {
+    Floor::parse(s).unwrap().0.len()
+}
+pub fn part2(s: &str) -> usize {
+    let mut floor = Generations::from_initial(Floor::parse(s).unwrap());
+    for _ in 0..100 {
+        floor.step(Floor::step);
+    }
+    floor.current().0.len()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Direction {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+const ALL_DIRECTIONS: &[Direction] = &[
+    Direction::East,
+    Direction::SouthEast,
+    Direction::SouthWest,
+    Direction::West,
+    Direction::NorthWest,
+    Direction::NorthEast,
+];
+
+/// Axial coordinates: `q` increases to the east, `r` increases to the south-east.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+struct Hex {
+    q: i32,
+    r: i32,
+}
+impl Hex {
+    fn neighbour(&self, direction: Direction) -> Hex {
+        let (dq, dr) = match direction {
+            Direction::East => (1, 0),
+            Direction::SouthEast => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (0, -1),
+            Direction::NorthEast => (1, -1),
+        };
+        Hex {
+            q: self.q + dq,
+            r: self.r + dr,
+        }
+    }
+    fn neighbours(self) -> impl Iterator<Item = Hex> {
+        ALL_DIRECTIONS.iter().map(move |&d| self.neighbour(d))
+    }
+}
+
+fn parse_path(s: &str) -> Result<Vec<Direction>> {
+    let mut output = Vec::new();
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        output.push(match b {
+            b'e' => Direction::East,
+            b'w' => Direction::West,
+            b'n' | b's' => match (b, bytes.next()) {
+                (b'n', Some(b'e')) => Direction::NorthEast,
+                (b'n', Some(b'w')) => Direction::NorthWest,
+                (b's', Some(b'e')) => Direction::SouthEast,
+                (b's', Some(b'w')) => Direction::SouthWest,
+                (_, next) => bail!(
+                    "Unrecognised direction: {}{}",
+                    b as char,
+                    next.map_or(String::new(), |n| (n as char).to_string())
+                ),
+            },
+            _ => bail!("Unrecognised direction: {}", b as char),
+        });
+    }
+    Ok(output)
+}
+
+/// The set of black tiles.
+#[derive(Debug, Clone, PartialEq)]
+struct Floor(HashSet<Hex>);
+impl Floor {
+    fn parse(s: &str) -> Result<Floor> {
+        let mut black = HashSet::new();
+        for line in s.lines() {
+            let tile = parse_path(line)?
+                .into_iter()
+                .fold(Hex::default(), |h, d| h.neighbour(d));
+            if !black.insert(tile) {
+                black.remove(&tile);
+            }
+        }
+        Ok(Floor(black))
+    }
+    fn step(source: &Floor, dest: &mut Floor) {
+        let mut neighbour_counts = HashMap::new();
+        for tile in source.0.iter().flat_map(|t| t.neighbours()) {
+            *neighbour_counts.entry(tile).or_insert(0) += 1;
+        }
+        dest.0.clear();
+        dest.0.extend(
+            neighbour_counts
+                .into_iter()
+                .filter(|(tile, count)| {
+                    // black stays black with 1 or 2 black neighbours; white turns black with 2
+                    matches!((source.0.contains(tile), count), (true, 1) | (_, 2))
+                })
+                .map(|(tile, _)| tile),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "sesenwnenenewseeswwswswwnenewsewsw
+neeenesenwnwwswnenewnwwsewnenwseswesw
+seswneswswsenwwnwse
+nwnwneseeswswnenewneswwnewseswneseene
+swweswneswnenwsewnwneneseenw
+eesenwseswswnenwswnwnwsewwnwsene
+sewnenenenesenwsewnenwwwse
+wenwwweseeeweswwwnwwe
+wsweesenenewnwwnwsenewsenwwsesesenwne
+neeswseenwwswnwswswnw
+nenwswwsewswnenenewsenwsenwnesesenew
+enewnwewneswsewnwswenweswnenwsenwsw
+sweneswneswneneenwnewenewwneswswnese
+swwesenesewenwneswnwwneseswwne
+enesenwswwswneneswsenwnewswseenwsese
+wnwnesenesenenwwnenwsewesewsesesew
+nenewswnwewswnenesenwnesewesw
+eneswnwswnwsenenwnwnwwseeswneewsenese
+neswnwewnwnwseenwseesewsenwsweewe
+wseweeenwnesenwwwswnew";
+
+    #[test]
+    fn can_parse_path() {
+        assert_eq!(
+            vec![Direction::East, Direction::SouthEast, Direction::West],
+            parse_path("esew").unwrap()
+        );
+        assert!(parse_path("ex").is_err());
+        assert!(parse_path("n").is_err());
+    }
+    #[test]
+    fn loops_back_to_origin() {
+        let tile = parse_path("nwwswee")
+            .unwrap()
+            .into_iter()
+            .fold(Hex::default(), |h, d| h.neighbour(d));
+        assert_eq!(Hex::default(), tile);
+    }
+    #[test]
+    fn gets_example_1() {
+        assert_eq!(10, part1(EXAMPLE));
+    }
+    #[test]
+    fn steps_example() {
+        let mut floor = Generations::from_initial(Floor::parse(EXAMPLE).unwrap());
+        floor.step(Floor::step);
+        assert_eq!(15, floor.current().0.len());
+        floor.step(Floor::step);
+        assert_eq!(12, floor.current().0.len());
+        while floor.step_count() < 10 {
+            floor.step(Floor::step);
+        }
+        assert_eq!(37, floor.current().0.len());
+    }
+    #[test]
+    fn gets_example_2() {
+        assert_eq!(2208, part2(EXAMPLE));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::util::modular::{discrete_log, mod_pow};
+use anyhow::{anyhow, ensure, Result};
+
+pub fn part1(s: &str) -> i128 {
+    find_encryption_key(s, discrete_log).unwrap()
+}
+
+const SUBJECT: i128 = 7;
+const MODULUS: i128 = 20201227;
+
+fn parse(s: &str) -> Result<(i128, i128)> {
+    let keys = s
+        .lines()
+        .map(str::parse)
+        .collect::<Result<Vec<i128>, _>>()?;
+    ensure!(keys.len() == 2, "Expected two public keys");
+    Ok((keys[0], keys[1]))
+}
+
+/// Recovers the card's loop size from its public key, and uses it to transform the door's
+/// public key.
+fn find_encryption_key(
+    s: &str,
+    find_loop_size: impl Fn(i128, i128, i128) -> Option<u64>,
+) -> Result<i128> {
+    let (card, door) = parse(s)?;
+    let loop_size = find_loop_size(SUBJECT, card, MODULUS)
+        .ok_or_else(|| anyhow!("No loop size produces public key {}", card))?;
+    Ok(mod_pow(door, loop_size, MODULUS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::modular::discrete_log_naive;
+
+    const EXAMPLE: &str = "5764801
+17807724";
+
+    #[test]
+    fn finds_loop_sizes() {
+        assert_eq!(Some(8), discrete_log(SUBJECT, 5764801, MODULUS));
+        assert_eq!(Some(11), discrete_log(SUBJECT, 17807724, MODULUS));
+    }
+    #[test]
+    fn gets_example() {
+        assert_eq!(14897079, part1(EXAMPLE));
+    }
+    #[test]
+    fn naive_search_agrees() {
+        assert_eq!(
+            14897079,
+            find_encryption_key(EXAMPLE, discrete_log_naive).unwrap()
+        );
+    }
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse("5764801").is_err());
+        assert!(parse("5764801\nasdf").is_err());
+    }
+}
@@ -1,30 +1,185 @@
-use std::ops::{Add, Index};
+use anyhow::{bail, ensure, Result};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    ops::{Add, Index, RangeInclusive},
+};
 
 pub fn part1(s: &str) -> usize {
-    count_trees(s, (3, 1).into())
+    count_trees(&TravelMap::parse(s).unwrap(), (3, 1).into())
 }
 pub fn part2(s: &str) -> usize {
-    [(1i32, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+    let slopes = [(1i32, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
         .iter()
-        .map(|t| count_trees(s, (*t).into()))
-        .fold(1, |prev, cur| prev * cur)
+        .map(|t| (*t).into())
+        .collect::<Vec<_>>();
+    count_trees_many(&TravelMap::parse(s).unwrap(), &slopes)
+        .iter()
+        .product()
 }
 
-fn count_trees(s: &str, offset: Vec2) -> usize {
-    get_path(&TravelMap::parse(s), offset.into())
-        .filter(|t| **t == Tile::Tree)
+fn count_trees(map: &TravelMap, offset: Vec2) -> usize {
+    get_path(map, offset)
+        .filter(|(_, t)| **t == Tile::Tree)
         .count()
 }
 
-fn get_path(map: &TravelMap, offset: Vec2) -> impl Iterator<Item = &Tile> {
-    std::iter::successors(Some(Vec2 { x: 0, y: 0 }), move |prev| Some(prev + &offset))
-        .take_while(move |v| v.y < map.trees.len() as i32)
-        .map(move |v| map.index(v))
+/// Counts the trees hit on each slope. Downward slopes on a map which only wraps sideways are
+/// all followed together in a single pass down the rows.
+fn count_trees_many(map: &TravelMap, slopes: &[Vec2]) -> Vec<usize> {
+    let sweep = |slope: &Vec2| map.wrap == Wrap::Horizontal && slope.y > 0;
+    let mut counts = slopes
+        .iter()
+        .map(|&slope| {
+            if sweep(&slope) {
+                0
+            } else {
+                count_trees(map, slope)
+            }
+        })
+        .collect::<Vec<_>>();
+    for row in 0..map.height {
+        for (count, slope) in counts.iter_mut().zip(slopes).filter(|(_, s)| sweep(s)) {
+            if row % slope.y as usize != 0 {
+                continue;
+            }
+            let step = (row / slope.y as usize) as i64;
+            if map.is_tree(step * slope.x as i64, row) {
+                *count += 1;
+            }
+        }
+    }
+    counts
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rank {
+    FewestTrees,
+    MostTrees,
+}
+
+/// Every downward slope with a step of `dx` columns and `dy` rows within the bounds, ranked by
+/// the number of trees hit.
+pub fn search_slopes(
+    map: &TravelMap,
+    dx: RangeInclusive<i32>,
+    dy: RangeInclusive<i32>,
+    rank: Rank,
+) -> Vec<(Vec2, usize)> {
+    let slopes = dy
+        .filter(|&y| y > 0)
+        .flat_map(|y| dx.clone().map(move |x| Vec2 { x, y }))
+        .collect::<Vec<_>>();
+    rank_slopes(map, slopes, rank)
+}
+
+/// Like [`search_slopes`], but over slopes of `columns / rows` columns per row, so that `1/2`
+/// and `2/4` are the same slope. Each is followed through the grid cells that it lands on.
+pub fn search_rational_slopes(
+    map: &TravelMap,
+    columns: RangeInclusive<i32>,
+    rows: RangeInclusive<i32>,
+    rank: Rank,
+) -> Vec<(Vec2, usize)> {
+    let mut seen = HashSet::new();
+    let slopes = rows
+        .flat_map(|q| columns.clone().filter_map(move |p| Vec2::from_ratio(p, q)))
+        .filter(|slope| seen.insert(*slope))
+        .collect::<Vec<_>>();
+    rank_slopes(map, slopes, rank)
+}
+
+fn rank_slopes(map: &TravelMap, slopes: Vec<Vec2>, rank: Rank) -> Vec<(Vec2, usize)> {
+    let counts = count_trees_many(map, &slopes);
+    let mut ranked = slopes.into_iter().zip(counts).collect::<Vec<_>>();
+    match rank {
+        Rank::FewestTrees => ranked.sort_by_key(|(_, count)| *count),
+        Rank::MostTrees => ranked.sort_by_key(|(_, count)| Reverse(*count)),
+    }
+    ranked
+}
+
+/// The positions visited, along with what is found there. The path starts in the top left
+/// corner, or the bottom left if it goes upward, and runs until it leaves the map or wraps
+/// back around to where it started.
+fn get_path(map: &TravelMap, offset: Vec2) -> impl Iterator<Item = (Vec2, &Tile)> {
+    let start = Vec2 {
+        x: 0,
+        y: if offset.y < 0 {
+            map.height as i32 - 1
+        } else {
+            0
+        },
+    };
+    let start_cell = map.cell(start);
+    std::iter::successors(Some(start), move |prev| Some(prev + &offset))
+        .enumerate()
+        .take_while(move |(idx, v)| {
+            let cell = map.cell(*v);
+            cell.is_some() && (*idx == 0 || cell != start_cell)
+        })
+        .map(move |(_, v)| (v, map.index(v)))
+}
+
+/// Draws the map with the path marked as in the puzzle text, `O` where it crosses open ground
+/// and `X` where it hits a tree, repeating the map sideways as far as the path goes.
+pub fn render_path(map: &TravelMap, offset: Vec2) -> String {
+    let path = get_path(map, offset)
+        .map(|(v, t)| {
+            // a path around a torus is drawn over a single copy of the map
+            let (x, y) = match map.wrap {
+                Wrap::Torus => map.cell(v).map(|(x, y)| (x as i32, y as i32)).unwrap(),
+                _ => (v.x, v.y),
+            };
+            ((x as i64, y as usize), t)
+        })
+        .collect::<HashMap<_, _>>();
+    let width = map.width as i64;
+    let (min_x, max_x) = path
+        .keys()
+        .fold((0, 0), |(lo, hi), &(x, _)| (lo.min(x), hi.max(x)));
+    // whole copies of the map, so the left edge of the first copy lines up with column 0
+    let cols = min_x.div_euclid(width) * width..(max_x.div_euclid(width) + 1) * width;
+    let mut out = String::new();
+    for row in 0..map.height {
+        if row > 0 {
+            out.push('\n');
+        }
+        out.extend(cols.clone().map(|col| match path.get(&(col, row)) {
+            Some(Tile::Tree) => 'X',
+            Some(Tile::Empty) => 'O',
+            None if map.is_tree(col, row) => '#',
+            None => '.',
+        }));
+    }
+    out
 }
 
-struct Vec2 {
-    x: i32,
-    y: i32,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Vec2 {
+    pub x: i32,
+    pub y: i32,
+}
+impl Vec2 {
+    /// The smallest step that lands on a grid cell when moving `columns / rows` columns per row
+    /// downward, if the slope goes downward at all.
+    pub fn from_ratio(columns: i32, rows: i32) -> Option<Vec2> {
+        if rows <= 0 {
+            return None;
+        }
+        let divisor = gcd(columns.unsigned_abs(), rows as u32) as i32;
+        Some(Vec2 {
+            x: columns / divisor,
+            y: rows / divisor,
+        })
+    }
+}
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
 }
 impl From<(i32, i32)> for Vec2 {
     fn from(t: (i32, i32)) -> Self {
@@ -41,30 +196,90 @@ impl Add for &Vec2 {
         }
     }
 }
-struct TravelMap {
+const WORD_BITS: usize = 64;
+
+/// What lies beyond the edges of the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Wrap {
+    /// The map repeats to the left and right, but not above or below.
+    Horizontal,
+    /// The map repeats in every direction.
+    Torus,
+    /// Nothing outside the map.
+    None,
+}
+
+/// The map as one bit per cell, set for trees, with each row padded out to whole words.
+pub struct TravelMap {
     width: usize,
-    trees: Vec<Vec<(usize, Tile)>>,
+    height: usize,
+    trees: Vec<u64>,
+    wrap: Wrap,
 }
 impl TravelMap {
-    fn parse(s: &str) -> TravelMap {
-        let width = s.lines().next().map_or(1, str::len);
-        let trees = s
-            .lines()
-            .map(|line| {
-                line.as_bytes()
-                    .iter()
-                    .enumerate()
-                    .filter(|(_, b)| **b == b'#')
-                    .map(|(col, _)| (col, Tile::Tree))
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        TravelMap { width, trees }
+    pub fn parse(s: &str) -> Result<TravelMap> {
+        let width = s.lines().next().map_or(0, str::len);
+        ensure!(width > 0, "Empty map");
+        let words_per_row = width.div_ceil(WORD_BITS);
+        let mut trees = Vec::new();
+        let mut height = 0;
+        for (row, line) in s.lines().enumerate() {
+            if let Some((col, c)) = line.char_indices().find(|(_, c)| !matches!(c, '.' | '#')) {
+                bail!("Unexpected {:?} at row {}, column {}", c, row + 1, col + 1)
+            }
+            ensure!(
+                line.len() == width,
+                "Row {} is {} wide, but the map is {} wide",
+                row + 1,
+                line.len(),
+                width
+            );
+            let start = trees.len();
+            trees.resize(start + words_per_row, 0);
+            for (col, _) in line.bytes().enumerate().filter(|(_, b)| *b == b'#') {
+                trees[start + col / WORD_BITS] |= 1 << (col % WORD_BITS);
+            }
+            height += 1;
+        }
+        Ok(TravelMap {
+            width,
+            height,
+            trees,
+            wrap: Wrap::Horizontal,
+        })
+    }
+    pub fn with_wrap(self, wrap: Wrap) -> TravelMap {
+        TravelMap { wrap, ..self }
+    }
+    /// The cell on the map at a position, after wrapping, if there is one.
+    fn cell(&self, v: Vec2) -> Option<(usize, usize)> {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let (x, y) = (v.x as i64, v.y as i64);
+        let (x, y) = match self.wrap {
+            Wrap::Horizontal => (x.rem_euclid(width), y),
+            Wrap::Torus => (x.rem_euclid(width), y.rem_euclid(height)),
+            Wrap::None => (x, y),
+        };
+        if (0..width).contains(&x) && (0..height).contains(&y) {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+    fn words_per_row(&self) -> usize {
+        self.width.div_ceil(WORD_BITS)
+    }
+    /// Whether there is a tree at `col`, wrapped around the width of the map, in an existing
+    /// `row`.
+    fn is_tree(&self, col: i64, row: usize) -> bool {
+        let col = col.rem_euclid(self.width as i64) as usize;
+        let word = self.trees[row * self.words_per_row() + col / WORD_BITS];
+        word & (1 << (col % WORD_BITS)) != 0
     }
 }
 
 #[derive(Debug, PartialEq)]
-enum Tile {
+pub enum Tile {
     Empty,
     Tree,
 }
@@ -73,19 +288,9 @@ impl Index<Vec2> for TravelMap {
     type Output = Tile;
 
     fn index(&self, index: Vec2) -> &Self::Output {
-        if index.y < 0 || index.y as usize >= self.trees.len() {
-            &Tile::Empty
-        } else {
-            let col = index.x % self.width as i32;
-            let col = if col < 0 {
-                col + self.width as i32
-            } else {
-                col
-            } as usize;
-            self.trees[index.y as usize]
-                .iter()
-                .find(|(tree_col, _)| col == *tree_col)
-                .map_or(&Tile::Empty, |(_, t)| t)
+        match self.cell(index) {
+            Some((col, row)) if self.is_tree(col as i64, row) => &Tile::Tree,
+            _ => &Tile::Empty,
         }
     }
 }
@@ -106,20 +311,39 @@ mod tests {
 .#..#...#.#";
     #[test]
     fn can_parse() {
-        let m = TravelMap::parse(EXAMPLE);
+        let m = TravelMap::parse(EXAMPLE).unwrap();
         assert_eq!(11, m.width);
-        assert_eq!(11, m.trees.len());
+        assert_eq!(11, m.height);
         assert_eq!(
-            [0usize, 4, 8]
-                .iter()
-                .map(|i| (*i, Tile::Tree))
-                .collect::<Vec<_>>(),
-            m.trees[1]
+            vec![0, 4, 8],
+            (0..11).filter(|&col| m.is_tree(col, 1)).collect::<Vec<_>>()
         )
     }
     #[test]
+    fn rejects_bad_maps() {
+        assert!(TravelMap::parse("").is_err());
+        assert!(TravelMap::parse("..#\n.#").is_err());
+        assert!(TravelMap::parse("..#\n.#..").is_err());
+        assert!(TravelMap::parse("..#\n\n..#").is_err());
+        assert_eq!(
+            "Unexpected 'é' at row 2, column 2",
+            TravelMap::parse("..#\n.é.").err().unwrap().to_string()
+        );
+        assert!(TravelMap::parse("..#\r\n.#.\n").is_ok());
+    }
+    #[test]
+    fn can_index_wide_rows() {
+        let row = format!("{}#{}#", ".".repeat(64), ".".repeat(65));
+        let m = TravelMap::parse(&format!("{}\n{}", row, row)).unwrap();
+        assert_eq!(3, m.words_per_row());
+        assert!(m.is_tree(64, 1));
+        assert!(m.is_tree(130, 0));
+        assert!(m.is_tree(-1, 0));
+        assert!(!m.is_tree(65, 0));
+    }
+    #[test]
     fn can_index() {
-        let m = TravelMap::parse(EXAMPLE);
+        let m = TravelMap::parse(EXAMPLE).unwrap();
         assert_eq!(
             Tile::Empty,
             m[(0, 0).into()],
@@ -156,16 +380,123 @@ mod tests {
     fn gets_example_path() {
         const T: Tile = Tile::Tree;
         const E: Tile = Tile::Empty;
-        assert!(vec![E, E, T, E, T, T, E, T, T, T, T,]
-            .iter()
-            .eq(get_path(&TravelMap::parse(EXAMPLE), (3, 1).into())))
+        assert!(vec![E, E, T, E, T, T, E, T, T, T, T,].iter().eq(get_path(
+            &TravelMap::parse(EXAMPLE).unwrap(),
+            (3, 1).into()
+        )
+        .map(|(_, t)| t)))
     }
 
+    #[test]
+    fn gets_path_positions() {
+        let m = TravelMap::parse(EXAMPLE).unwrap();
+        assert_eq!(
+            vec![(0, 0), (1, 2), (2, 4), (3, 6), (4, 8), (5, 10)],
+            get_path(&m, (1, 2).into())
+                .map(|(v, _)| (v.x, v.y))
+                .collect::<Vec<_>>()
+        );
+    }
+    #[test]
+    fn renders_example_path() {
+        assert_eq!(
+            "O.##.........##.........##.......
+#..O#...#..#...#...#..#...#...#..
+.#....X..#..#....#..#..#....#..#.
+..#.#...#O#..#.#...#.#..#.#...#.#
+.#...##..#..X...##..#..#...##..#.
+..#.##.......#.X#.......#.##.....
+.#.#.#....#.#.#.#.O..#.#.#.#....#
+.#........#.#........X.#........#
+#.##...#...#.##...#...#.X#...#...
+#...##....##...##....##...#X....#
+.#..#...#.#.#..#...#.#.#..#...X.#",
+            render_path(&TravelMap::parse(EXAMPLE).unwrap(), (3, 1).into())
+        );
+    }
+    #[test]
+    fn renders_leftward_path() {
+        assert_eq!(
+            "..O.\n#O#.",
+            render_path(&TravelMap::parse("..\n#.").unwrap(), (-1, 1).into())
+        );
+    }
+    #[test]
+    fn follows_wrap_modes() {
+        let path = |wrap, slope: (i32, i32)| {
+            let m = TravelMap::parse(EXAMPLE).unwrap().with_wrap(wrap);
+            get_path(&m, slope.into())
+                .map(|(v, _)| (v.x, v.y))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(0, 0), (3, 1), (6, 2), (9, 3)],
+            path(Wrap::None, (3, 1))
+        );
+        assert_eq!(11, path(Wrap::Horizontal, (3, 1)).len());
+        assert_eq!(11, path(Wrap::Horizontal, (3, 0)).len());
+        assert_eq!(vec![(0, 10), (3, 9)], path(Wrap::Horizontal, (3, -1))[..2]);
+        assert_eq!(11, path(Wrap::Horizontal, (3, -1)).len());
+        assert_eq!(11, path(Wrap::Torus, (3, 1)).len());
+        assert_eq!(11, path(Wrap::Torus, (1, 11)).len());
+        assert_eq!(vec![(0, 0)], path(Wrap::Torus, (0, 0)));
+    }
+    #[test]
+    fn counts_trees_with_wrap_modes() {
+        let m = TravelMap::parse("#.\n.#\n..")
+            .unwrap()
+            .with_wrap(Wrap::Torus);
+        assert_eq!(
+            vec![2, 1, 2],
+            count_trees_many(&m, &[(1, 1).into(), (0, 1).into(), (1, -1).into()])
+        );
+        assert_eq!("XO\nOX\nOO", render_path(&m, (1, 1).into()));
+    }
     #[test]
     fn gets_part_1_example() {
         assert_eq!(7, part1(EXAMPLE))
     }
     #[test]
+    fn counts_many_slopes_at_once() {
+        let m = TravelMap::parse(EXAMPLE).unwrap();
+        let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+            .iter()
+            .map(|t| (*t).into())
+            .collect::<Vec<Vec2>>();
+        assert_eq!(vec![2, 7, 3, 4, 2], count_trees_many(&m, &slopes));
+        for slope in slopes {
+            assert_eq!(count_trees(&m, slope), count_trees_many(&m, &[slope])[0]);
+        }
+    }
+    #[test]
+    fn reduces_ratios() {
+        assert_eq!(Some((1, 2).into()), Vec2::from_ratio(2, 4));
+        assert_eq!(Some((-3, 1).into()), Vec2::from_ratio(-6, 2));
+        assert_eq!(Some((0, 1).into()), Vec2::from_ratio(0, 5));
+        assert_eq!(None, Vec2::from_ratio(1, 0));
+    }
+    #[test]
+    fn ranks_slopes() {
+        let m = TravelMap::parse(EXAMPLE).unwrap();
+        let most = search_slopes(&m, 1..=7, 1..=1, Rank::MostTrees);
+        assert_eq!(7, most.len());
+        assert_eq!(((3, 1).into(), 7), most[0]);
+        let fewest = search_slopes(&m, 1..=7, 0..=1, Rank::FewestTrees);
+        assert_eq!(most.len(), fewest.len());
+        assert_eq!(most.last().unwrap().1, fewest[0].1);
+        assert!(fewest.windows(2).all(|w| w[0].1 <= w[1].1));
+    }
+    #[test]
+    fn ranks_rational_slopes() {
+        let m = TravelMap::parse(EXAMPLE).unwrap();
+        let ranked = search_rational_slopes(&m, 1..=2, 1..=2, Rank::MostTrees);
+        // 2/2 is the same slope as 1/1
+        assert_eq!(3, ranked.len());
+        assert!(ranked.contains(&((1, 1).into(), 2)));
+        assert!(ranked.contains(&((1, 2).into(), 2)));
+        assert!(ranked.contains(&((2, 1).into(), count_trees(&m, (2, 1).into()))));
+    }
+    #[test]
     fn gets_part_2_example() {
         assert_eq!(336, part2(EXAMPLE))
     }
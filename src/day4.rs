@@ -1,146 +1,418 @@
-use anyhow::{bail, Context, Error, Result};
-use std::{
-    collections::HashSet,
-    mem::{discriminant, Discriminant},
-    str::FromStr,
-};
-
-pub fn part1(s: &'static str) -> usize {
-    parse(s, "\n\n")
-        .filter(|r| r.as_ref().unwrap().is_valid())
+pub mod export;
+pub mod identity;
+mod schema;
+
+pub use schema::Schema;
+
+use anyhow::{bail, Error, Result};
+use std::{collections::HashSet, fmt::Display, io::BufRead, str::FromStr};
+
+pub fn part1(s: &str) -> usize {
+    let schema = puzzle_schema();
+    parse(s, Mode::Lenient)
+        .filter(|r| r.as_ref().unwrap().is_valid(&schema))
         .count()
 }
-pub fn part2(s: &'static str) -> usize {
-    parse(s, "\n\n")
+pub fn part2(s: &str) -> usize {
+    let schema = puzzle_schema();
+    parse(s, Mode::Lenient)
         .filter(|r| {
             let record = r.as_ref().unwrap();
-            record.is_valid() && record.is_contents_valid()
+            record.is_valid(&schema) && record.is_contents_valid(&schema)
         })
         .count()
 }
 
+/// Every record of a batch file, numbered from 1, with whatever is wrong with it.
+pub fn report<'a>(
+    s: &'a str,
+    schema: &'a Schema,
+    mode: Mode,
+) -> impl Iterator<Item = RecordReport> + 'a {
+    parse_for(s, schema, mode)
+        .enumerate()
+        .map(move |(idx, record)| RecordReport {
+            record: idx + 1,
+            outcome: record.map(|r| r.problems(schema)),
+        })
+}
+
+pub struct RecordReport {
+    pub record: usize,
+    pub outcome: Result<Vec<Problem>>,
+}
+impl Display for RecordReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "record {}: ", self.record)?;
+        match &self.outcome {
+            Ok(problems) if problems.is_empty() => write!(f, "valid"),
+            Ok(problems) => {
+                for (idx, problem) in problems.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", problem)?;
+                }
+                Ok(())
+            }
+            Err(e) => write!(f, "parse error: {}", e),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
-enum Length {
+pub enum Problem {
+    Missing(String),
+    Duplicate(String),
+    Invalid { field: String, reason: String },
+}
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::Missing(key) => write!(f, "missing {}", key),
+            Problem::Duplicate(key) => write!(f, "duplicate {}", key),
+            Problem::Invalid { field, reason } => write!(f, "{} {}", field, reason),
+        }
+    }
+}
+
+fn puzzle_schema() -> Schema {
+    Schema::parse(include_str!("data\\day4-schema.txt")).unwrap()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Length {
     Centimetre(i32),
     Inch(i32),
     Unknown(i32),
 }
-#[derive(Debug, PartialEq, Eq)]
-enum Field {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Centimetre,
+    Inch,
+}
+/// How to treat fields with unrecognised keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Fail to parse the record, to catch typos.
+    Strict,
+    /// Keep the field as [`Field::Unknown`], which validation ignores unless the schema
+    /// declares its key.
+    Lenient,
+}
+
+/// A field of a passport, borrowing its text from the batch it was parsed from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field<'a> {
     BirthYear(i64),
     IssueYear(i64),
     ExpirationYear(i64),
     Height(Length),
-    HairColour(String),
-    EyeColour(String),
-    PassportId(String),
+    HairColour(&'a str),
+    EyeColour(&'a str),
+    PassportId(&'a str),
     CountryId(i64),
+    /// A known key whose value is not of the right type, kept as text so that it can be
+    /// reported.
+    Unparsed(&'a str, &'a str),
+    Unknown(&'a str, &'a str),
 }
 
-#[derive(Debug)]
-struct Record(Vec<Field>);
+#[derive(Debug, PartialEq, Eq)]
+pub struct Record<'a>(Vec<Field<'a>>);
 
 impl FromStr for Length {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.ends_with("cm") {
-            Ok(Length::Centimetre(s[..s.len() - 2].parse()?))
-        } else if s.ends_with("in") {
-            Ok(Length::Inch(s[..s.len() - 2].parse()?))
+        if let Some(cm) = s.strip_suffix("cm") {
+            Ok(Length::Centimetre(cm.parse()?))
+        } else if let Some(inch) = s.strip_suffix("in") {
+            Ok(Length::Inch(inch.parse()?))
         } else {
             Ok(Length::Unknown(s.parse()?))
         }
     }
 }
-impl FromStr for Field {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl Unit {
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Unit::Centimetre => "cm",
+            Unit::Inch => "in",
+        }
+    }
+}
+impl Length {
+    /// The length in micrometres, which measure both units exactly, if the unit is known.
+    pub fn micrometres(self) -> Option<i64> {
+        match self {
+            Length::Centimetre(cm) => Some(i64::from(cm) * 10_000),
+            Length::Inch(inch) => Some(i64::from(inch) * 25_400),
+            Length::Unknown(_) => None,
+        }
+    }
+    pub fn centimetres(self) -> Option<f64> {
+        self.micrometres().map(|um| um as f64 / 10_000.0)
+    }
+    pub fn inches(self) -> Option<f64> {
+        self.micrometres().map(|um| um as f64 / 25_400.0)
+    }
+    /// Gives a unitless length the unit, leaving other lengths alone.
+    pub fn assume(self, unit: Unit) -> Length {
+        match (self, unit) {
+            (Length::Unknown(n), Unit::Centimetre) => Length::Centimetre(n),
+            (Length::Unknown(n), Unit::Inch) => Length::Inch(n),
+            (known, _) => known,
+        }
+    }
+}
+impl<'a> Field<'a> {
+    pub fn parse(s: &'a str, mode: Mode) -> Result<Field<'a>> {
         let tokens: Vec<_> = s.splitn(2, ':').collect();
         if tokens.len() != 2 {
             bail!("Not colon-delimited")
         }
-        let ctx = || format!("Failed to parse {}:{}", tokens[0], tokens[1]);
-        match tokens[0] {
-            "byr" => Ok(Field::BirthYear(tokens[1].parse().with_context(ctx)?)),
-            "iyr" => Ok(Field::IssueYear(tokens[1].parse().with_context(ctx)?)),
-            "eyr" => Ok(Field::ExpirationYear(tokens[1].parse().with_context(ctx)?)),
-            "hgt" => Ok(Field::Height(tokens[1].parse().with_context(ctx)?)),
-            "hcl" => Ok(Field::HairColour(tokens[1].to_owned())),
-            "ecl" => Ok(Field::EyeColour(tokens[1].to_owned())),
-            "pid" => Ok(Field::PassportId(tokens[1].to_owned())),
-            "cid" => Ok(Field::CountryId(tokens[1].parse().with_context(ctx)?)),
-            _ => bail!("unrecognised field {}", tokens[0]),
+        let (key, value) = (tokens[0], tokens[1]);
+        // a typed value which doesn't parse is kept, so that validation can report it
+        let unparsed = Field::Unparsed(key, value);
+        match key {
+            "byr" => Ok(value.parse().map_or(unparsed, Field::BirthYear)),
+            "iyr" => Ok(value.parse().map_or(unparsed, Field::IssueYear)),
+            "eyr" => Ok(value.parse().map_or(unparsed, Field::ExpirationYear)),
+            "hgt" => Ok(value.parse().map_or(unparsed, Field::Height)),
+            "hcl" => Ok(Field::HairColour(value)),
+            "ecl" => Ok(Field::EyeColour(value)),
+            "pid" => Ok(Field::PassportId(value)),
+            "cid" => Ok(value.parse().map_or(unparsed, Field::CountryId)),
+            key if mode == Mode::Lenient => Ok(Field::Unknown(key, tokens[1])),
+            _ => bail!("unrecognised field {}", key),
         }
     }
 }
-impl FromStr for Record {
-    type Err = Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
+impl<'a> Record<'a> {
+    /// Parses the fields of one record, which may be split over several lines.
+    pub fn parse(s: &'a str, mode: Mode) -> Result<Record<'a>> {
         s.split_whitespace()
-            .map(Field::from_str)
+            .map(|field| Field::parse(field, mode))
             .collect::<Result<Vec<_>>>()
             .map(Record)
     }
 }
-impl Field {
-    fn is_valid(&self) -> bool {
-        fn is_rgb(s: &String) -> bool {
-            let b = s.as_bytes();
-            b[0] == b'#'
-                && b[1..].len() == 6
-                && b[1..].iter().all(|b| (*b as char).is_ascii_hexdigit())
+impl Display for Length {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Length::Centimetre(cm) => write!(f, "{}cm", cm),
+            Length::Inch(inch) => write!(f, "{}in", inch),
+            Length::Unknown(n) => write!(f, "{}", n),
         }
-        fn is_eyecolour(s: &String) -> bool {
-            match s.as_str() {
-                "amb" => true,
-                "blu" => true,
-                "brn" => true,
-                "gry" => true,
-                "grn" => true,
-                "hzl" => true,
-                "oth" => true,
-                _ => false,
+    }
+}
+impl Display for Field<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.key(), self.value())
+    }
+}
+impl Display for Record<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, field) in self.0.iter().enumerate() {
+            if idx > 0 {
+                write!(f, " ")?;
             }
+            write!(f, "{}", field)?;
+        }
+        Ok(())
+    }
+}
+impl<'a> Field<'a> {
+    fn key(&self) -> &'a str {
+        match self {
+            Field::BirthYear(_) => "byr",
+            Field::IssueYear(_) => "iyr",
+            Field::ExpirationYear(_) => "eyr",
+            Field::Height(_) => "hgt",
+            Field::HairColour(_) => "hcl",
+            Field::EyeColour(_) => "ecl",
+            Field::PassportId(_) => "pid",
+            Field::CountryId(_) => "cid",
+            Field::Unparsed(key, _) | Field::Unknown(key, _) => key,
         }
-        fn is_passport_id(s: &String) -> bool {
-            s.len() == 9 && s.chars().all(|c| c.is_ascii_digit())
+    }
+    fn value(&self) -> String {
+        match self {
+            Field::BirthYear(n)
+            | Field::IssueYear(n)
+            | Field::ExpirationYear(n)
+            | Field::CountryId(n) => n.to_string(),
+            Field::Height(length) => length.to_string(),
+            Field::HairColour(s)
+            | Field::EyeColour(s)
+            | Field::PassportId(s)
+            | Field::Unparsed(_, s)
+            | Field::Unknown(_, s) => (*s).to_owned(),
         }
+    }
+    /// Why the value didn't parse, for a field that is [`Field::Unparsed`].
+    fn parse_failure(&self) -> Option<&'static str> {
         match self {
-            Field::BirthYear(year) => *year >= 1920 && *year <= 2002,
-            Field::IssueYear(year) => *year >= 2010 && *year <= 2020,
-            Field::ExpirationYear(year) => *year >= 2020 && *year <= 2030,
-            Field::Height(Length::Centimetre(cm)) => *cm >= 150 && *cm <= 193,
-            Field::Height(Length::Inch(inch)) => *inch >= 59 && *inch <= 76,
-            Field::Height(_) => false,
-            Field::HairColour(s) => is_rgb(&s),
-            Field::EyeColour(s) => is_eyecolour(&s),
-            Field::PassportId(s) => is_passport_id(&s),
-            Field::CountryId(_) => true,
+            Field::Unparsed("hgt", _) => Some("is not a height"),
+            Field::Unparsed(_, _) => Some("is not a number"),
+            _ => None,
         }
     }
+    /// Whether the schema has a rule for this field, and the field follows it.
+    fn is_valid(&self, schema: &Schema) -> bool {
+        self.parse_failure().is_none()
+            && schema
+                .rule(self.key())
+                .is_some_and(|rule| rule.accepts(&self.value()))
+    }
 }
-impl Record {
-    fn is_valid(&self) -> bool {
-        let all_fields = self.0.iter().map(discriminant).collect::<HashSet<_>>();
-        const FIELD_KIND_COUNT: usize = 8;
-        // JB 2021-01-01: morally const, but std::mem::discriminant is not const fn.
-        let optional_field: Discriminant<Field> =
-            discriminant(&Field::CountryId(Default::default()));
-        (all_fields.len() == self.0.len()) // duplicate field kinds not allowed
-            && (all_fields.len() == FIELD_KIND_COUNT // has all the fields, or
-                // is missing only the optional field
-                || (all_fields.len() == FIELD_KIND_COUNT - 1 && !all_fields.contains(&optional_field)))
-    }
-    fn is_contents_valid(&self) -> bool {
-        self.0.iter().all(Field::is_valid)
+impl<'a> Record<'a> {
+    pub fn fields(&self) -> &[Field<'a>] {
+        &self.0
+    }
+    /// The record's first passport ID, if it has one.
+    pub fn pid(&self) -> Option<&'a str> {
+        self.0.iter().find_map(|f| match f {
+            Field::PassportId(pid) => Some(*pid),
+            _ => None,
+        })
+    }
+    /// The fields that validation looks at, which excludes unknown fields that the schema does
+    /// not mention.
+    fn known_fields<'s>(&'s self, schema: &'s Schema) -> impl Iterator<Item = &'s Field<'a>> {
+        self.0.iter().filter(move |f| match f {
+            Field::Unknown(key, _) => schema.rule(key).is_some(),
+            _ => true,
+        })
+    }
+    /// Fails on the first unknown field that the schema does not mention.
+    fn check_keys(&self, schema: &Schema) -> Result<()> {
+        for field in &self.0 {
+            if let Field::Unknown(key, _) = field {
+                if schema.rule(key).is_none() {
+                    bail!("unrecognised field {}", key)
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Whether every required field is present, with no field repeated.
+    pub fn is_valid(&self, schema: &Schema) -> bool {
+        let keys = self
+            .known_fields(schema)
+            .map(Field::key)
+            .collect::<Vec<_>>();
+        keys.iter().collect::<HashSet<_>>().len() == keys.len()
+            && schema.required_keys().all(|key| keys.contains(&key))
+    }
+    pub fn is_contents_valid(&self, schema: &Schema) -> bool {
+        self.known_fields(schema).all(|f| f.is_valid(schema))
     }
+    /// Missing fields in schema order, then repeated and invalid fields in record order.
+    pub fn problems(&self, schema: &Schema) -> Vec<Problem> {
+        let keys = self
+            .known_fields(schema)
+            .map(Field::key)
+            .collect::<Vec<_>>();
+        let missing = schema
+            .required_keys()
+            .filter(|key| !keys.contains(key))
+            .map(|key| Problem::Missing(key.to_owned()));
+        let duplicates = keys
+            .iter()
+            .enumerate()
+            .filter(|(idx, key)| keys[..*idx].iter().filter(|k| k == key).count() == 1)
+            .map(|(_, key)| Problem::Duplicate((*key).to_owned()));
+        let invalid = self.known_fields(schema).filter_map(|field| {
+            let value = field.value();
+            let reason = match schema.rule(field.key()) {
+                Some(rule) => match rule.check(&value) {
+                    Ok(()) => field.parse_failure()?.to_owned(),
+                    Err(reason) => reason,
+                },
+                None => "is not in the schema".to_owned(),
+            };
+            Some(Problem::Invalid {
+                field: format!("{}:{}", field.key(), value),
+                reason,
+            })
+        });
+        missing.chain(duplicates).chain(invalid).collect()
+    }
+}
+/// The records of a batch file, which are separated by one or more blank lines.
+pub fn parse(s: &str, mode: Mode) -> impl Iterator<Item = Result<Record<'_>>> {
+    let mut lines = s.lines().peekable();
+    std::iter::from_fn(move || {
+        while lines.next_if(|line| line.trim().is_empty()).is_some() {}
+        lines.peek()?;
+        let mut fields = Ok(Vec::new());
+        for line in lines.by_ref().take_while(|line| !line.trim().is_empty()) {
+            fields = fields.and_then(|mut fields: Vec<_>| {
+                for field in line.split_whitespace() {
+                    fields.push(Field::parse(field, mode)?);
+                }
+                Ok(fields)
+            });
+        }
+        Some(fields.map(Record))
+    })
 }
-fn parse(s: &'static str, double_newline: &'static str) -> impl Iterator<Item = Result<Record>> {
-    s.split(double_newline).map(str::parse)
+
+/// Like [`parse`], but with any key that the schema declares counting as recognised.
+pub fn parse_for<'a>(
+    s: &'a str,
+    schema: &'a Schema,
+    mode: Mode,
+) -> impl Iterator<Item = Result<Record<'a>>> {
+    parse(s, Mode::Lenient).map(move |record| {
+        let record = record?;
+        if mode == Mode::Strict {
+            record.check_keys(schema)?;
+        }
+        Ok(record)
+    })
 }
 
+/// Reads a batch a record at a time, so that only one record's text is held in memory.
+pub struct BatchReader<R> {
+    reader: R,
+    mode: Mode,
+    buffer: String,
+}
+impl<R: BufRead> BatchReader<R> {
+    pub fn new(reader: R, mode: Mode) -> BatchReader<R> {
+        BatchReader {
+            reader,
+            mode,
+            buffer: String::new(),
+        }
+    }
+    /// The next record, which borrows from the reader until the following call.
+    pub fn next_record(&mut self) -> Option<Result<Record<'_>>> {
+        self.buffer.clear();
+        loop {
+            let start = self.buffer.len();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => break,
+                Ok(_) if self.buffer[start..].trim().is_empty() => {
+                    self.buffer.truncate(start);
+                    if start > 0 {
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(Record::parse(&self.buffer, self.mode))
+        }
+    }
+}
 #[cfg(test)]
 mod tests {
     use std::{
@@ -152,33 +424,54 @@ mod tests {
 
     #[test]
     fn can_parse_numeric_field() {
-        assert_eq!(Field::BirthYear(1234), "byr:1234".parse().unwrap())
+        assert_eq!(
+            Field::BirthYear(1234),
+            Field::parse("byr:1234", Mode::Strict).unwrap()
+        )
     }
     #[test]
     fn can_parse_string_field() {
         assert_eq!(
-            Field::HairColour("Gross".to_string()),
-            "hcl:Gross".parse().unwrap()
+            Field::HairColour("Gross"),
+            Field::parse("hcl:Gross", Mode::Strict).unwrap()
         )
     }
     #[test]
     fn can_parse_length_field() {
         assert_eq!(
             Field::Height(Length::Centimetre(123)),
-            "hgt:123cm".parse().unwrap()
+            Field::parse("hgt:123cm", Mode::Strict).unwrap()
         )
     }
     #[test]
+    fn converts_lengths() {
+        assert_eq!(Some(2.54), Length::Inch(1).centimetres());
+        assert_eq!(Some(59.0), Length::Centimetre(150).inches().map(f64::round));
+        assert_eq!(
+            Length::Inch(60).micrometres(),
+            Length::Centimetre(1524).micrometres().map(|um| um / 10)
+        );
+        assert_eq!(None, Length::Unknown(150).centimetres());
+        assert_eq!(Length::Inch(60), Length::Unknown(60).assume(Unit::Inch));
+        assert_eq!(
+            Length::Centimetre(60),
+            Length::Centimetre(60).assume(Unit::Inch)
+        );
+    }
+    #[test]
     fn can_parse_record_line() {
         assert!(vec![Field::BirthYear(1), Field::IssueYear(1)]
             .iter()
-            .eq("byr:1 iyr:1".parse::<Record>().unwrap().0.iter()))
+            .eq(Record::parse("byr:1 iyr:1", Mode::Strict).unwrap().0.iter()))
     }
     #[test]
     fn can_parse_record_multiline() {
         assert!(vec![Field::BirthYear(1), Field::IssueYear(1)]
             .iter()
-            .eq("byr:1\niyr:1".parse::<Record>().unwrap().0.iter()))
+            .eq(Record::parse("byr:1\niyr:1", Mode::Strict)
+                .unwrap()
+                .0
+                .iter()))
     }
 
     const EXAMPLE: &str = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd
@@ -199,7 +492,7 @@ iyr:2011 ecl:brn hgt:59in";
     fn can_parse_records() {
         // JB 2021-01-01: unclear why Rust is not finding any CRs in the above string.
         // I'm pretty sure there's CRs in the *source*.
-        let result = parse(EXAMPLE, "\n\n").collect::<Result<Vec<_>>>();
+        let result = parse(EXAMPLE, Mode::Strict).collect::<Result<Vec<_>>>();
         assert_eq!(result.unwrap().len(), 4)
     }
     #[test]
@@ -213,10 +506,10 @@ iyr:2011 ecl:brn hgt:59in";
     }
     #[test]
     fn record_invalid_with_missing_fields() {
-        assert!(!Record(Default::default()).is_valid())
+        assert!(!Record(Default::default()).is_valid(&puzzle_schema()))
     }
-    impl Record {
-        fn minimal() -> Record {
+    impl Record<'static> {
+        fn minimal() -> Record<'static> {
             Record(vec![
                 Field::BirthYear(0),
                 Field::IssueYear(0),
@@ -230,19 +523,19 @@ iyr:2011 ecl:brn hgt:59in";
     }
     #[test]
     fn record_valid_with_only_required_fields() {
-        assert!(Record::minimal().is_valid())
+        assert!(Record::minimal().is_valid(&puzzle_schema()))
     }
     #[test]
     fn record_valid_with_all_fields() {
         let mut r = Record::minimal();
         r.0.push(Field::CountryId(0));
-        assert!(r.is_valid())
+        assert!(r.is_valid(&puzzle_schema()))
     }
     #[test]
     fn record_invalid_with_duplicate() {
         let mut r = Record::minimal();
         r.0.push(Field::BirthYear(0));
-        assert!(!r.is_valid())
+        assert!(!r.is_valid(&puzzle_schema()))
     }
 
     #[test]
@@ -265,15 +558,222 @@ ecl:brn hgt:182cm pid:021572410 eyr:2020 byr:1992 cid:277
 hgt:59cm ecl:zzz
 eyr:2038 hcl:74454a iyr:2023
 pid:3556412378 byr:2007";
+        let schema = puzzle_schema();
         assert_eq!(
             4,
-            parse(EXAMPLE, "\n\n")
+            parse(EXAMPLE, Mode::Strict)
                 .flatten()
-                .filter(|r| r.is_valid() && !r.is_contents_valid())
+                .filter(|r| r.is_valid(&schema) && !r.is_contents_valid(&schema))
                 .count()
         )
     }
 
+    #[test]
+    fn validates_fields_against_schema() {
+        let schema = puzzle_schema();
+        let valid = |s: &str| Field::parse(s, Mode::Strict).unwrap().is_valid(&schema);
+        assert!(valid("byr:2002"));
+        assert!(!valid("byr:2003"));
+        assert!(valid("hgt:60in"));
+        assert!(valid("hgt:190cm"));
+        assert!(!valid("hgt:190in"));
+        assert!(!valid("hgt:190"));
+        assert!(valid("hcl:#123abc"));
+        assert!(!valid("hcl:#123abz"));
+        assert!(!valid("hcl:123abc"));
+        assert!(valid("ecl:brn"));
+        assert!(!valid("ecl:wat"));
+        assert!(valid("pid:000000001"));
+        assert!(!valid("pid:0123456789"));
+        let strict = Schema::parse("[hcl]\none of = #000000").unwrap();
+        assert!(!Field::parse("hcl:#123abc", Mode::Strict)
+            .unwrap()
+            .is_valid(&strict));
+        assert!(!Field::parse("byr:2000", Mode::Strict)
+            .unwrap()
+            .is_valid(&strict));
+    }
+
+    #[test]
+    fn reports_problems() {
+        let schema = puzzle_schema();
+        let lines = report(
+            "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f
+
+hgt:59cm ecl:zzz byr:1990 byr:2000 byr:2001
+eyr:2038 hcl:74454a iyr:2023 pid:3556412378
+
+byr:1990 xyz:1",
+            &schema,
+            Mode::Strict,
+        )
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "record 1: valid",
+                "record 2: duplicate byr; hgt:59cm outside 150–193cm; ecl:zzz is not one of amb, blu, brn, gry, grn, hzl, oth; eyr:2038 outside 2020–2030; hcl:74454a does not match #\\h{6}; iyr:2023 outside 2010–2020; pid:3556412378 does not match \\d{9}",
+                "record 3: parse error: unrecognised field xyz",
+            ],
+            lines
+        );
+    }
+    #[test]
+    fn reports_unparsable_values() {
+        let schema = puzzle_schema();
+        let lines = report(
+            "byr:19x0 iyr:2010 iyr:2011\n\nhgt:tall cid:x",
+            &schema,
+            Mode::Strict,
+        )
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                "record 1: missing eyr; missing hgt; missing hcl; missing ecl; missing pid; duplicate iyr; byr:19x0 is not a number",
+                "record 2: missing byr; missing iyr; missing eyr; missing hcl; missing ecl; missing pid; hgt:tall needs a unit of cm or in; cid:x is not a number",
+            ],
+            lines
+        );
+        // even a rule with no checks rejects a value of the wrong type
+        let lax = Schema::parse("[byr]").unwrap();
+        let record = Record::parse("byr:abc", Mode::Strict).unwrap();
+        assert!(!record.is_contents_valid(&lax));
+        assert_eq!(
+            vec![Problem::Invalid {
+                field: "byr:abc".to_owned(),
+                reason: "is not a number".to_owned()
+            }],
+            record.problems(&lax)
+        );
+        assert_eq!(0, part1("byr:abc"));
+        assert_eq!(0, part2("byr:abc"));
+    }
+    #[test]
+    fn reports_missing_fields() {
+        let record = Record::parse("byr:1990 cid:1", Mode::Strict).unwrap();
+        assert_eq!(
+            vec!["iyr", "eyr", "hgt", "hcl", "ecl", "pid"],
+            record
+                .problems(&puzzle_schema())
+                .iter()
+                .map(|p| match p {
+                    Problem::Missing(key) => key.as_str(),
+                    _ => panic!("Unexpected {}", p),
+                })
+                .collect::<Vec<_>>()
+        );
+        let unlisted = Schema::parse("[byr]").unwrap();
+        assert_eq!(
+            vec![Problem::Invalid {
+                field: "cid:1".to_owned(),
+                reason: "is not in the schema".to_owned()
+            }],
+            record.problems(&unlisted)
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_fields_when_lenient() {
+        assert!(Field::parse("xyz:1", Mode::Strict).is_err());
+        assert!(Record::parse("byr:1 xyz:1", Mode::Strict).is_err());
+        let record = Record::parse("byr:1990 xyz:1 xyz:2", Mode::Lenient).unwrap();
+        assert_eq!(Field::Unknown("xyz", "2"), record.0[2]);
+        // unknown fields are neither duplicates nor missing from the schema
+        let schema = Schema::parse("[byr]\nrange = 1920-2002").unwrap();
+        assert!(record.is_valid(&schema));
+        assert!(record.is_contents_valid(&schema));
+        assert!(record.problems(&schema).is_empty());
+        // a known key with a bad value is kept to be reported
+        assert_eq!(
+            Field::Unparsed("byr", "abc"),
+            Record::parse("byr:abc", Mode::Lenient).unwrap().0[0]
+        );
+    }
+    #[test]
+    fn treats_schema_keys_as_known() {
+        let schema = Schema::parse("[byr]\nrange = 1920-2002\n[nat]\none of = uk fr").unwrap();
+        for &mode in &[Mode::Strict, Mode::Lenient] {
+            let lines = report(
+                "byr:1990 nat:uk\n\nbyr:1990\n\nnat:de nat:uk byr:1990",
+                &schema,
+                mode,
+            )
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+            assert_eq!(
+                vec![
+                    "record 1: valid",
+                    "record 2: missing nat",
+                    "record 3: duplicate nat; nat:de is not one of uk, fr",
+                ],
+                lines
+            );
+        }
+        let record = Record::parse("byr:1990 nat:uk xyz:1", Mode::Lenient).unwrap();
+        assert!(record.is_valid(&schema));
+        assert!(record.is_contents_valid(&schema));
+        assert!(!Record::parse("byr:1990", Mode::Lenient)
+            .unwrap()
+            .is_valid(&schema));
+        assert!(report("byr:1990 nat:uk xyz:1", &schema, Mode::Strict)
+            .next()
+            .unwrap()
+            .outcome
+            .is_err());
+    }
+    #[test]
+    fn parts_tolerate_unknown_fields() {
+        const EXAMPLE: &str = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
+hcl:#623a2f nickname:Bob
+
+eyr:2029 ecl:blu byr:1989";
+        assert_eq!(1, part1(EXAMPLE));
+        assert_eq!(1, part2(EXAMPLE));
+    }
+
+    #[test]
+    fn splits_records_on_blank_lines() {
+        let records = parse("\n\nbyr:1\r\niyr:2\r\n\r\n  \n\nbyr:3\n", Mode::Strict)
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            vec![
+                Record(vec![Field::BirthYear(1), Field::IssueYear(2)]),
+                Record(vec![Field::BirthYear(3)])
+            ],
+            records
+        );
+        let mut records = parse("byr:1 xyz:2\niyr:3\n\nbyr:4", Mode::Strict);
+        assert!(records.next().unwrap().is_err());
+        assert_eq!(
+            Record(vec![Field::BirthYear(4)]),
+            records.next().unwrap().unwrap()
+        );
+        assert!(records.next().is_none());
+    }
+    #[test]
+    fn reads_records_from_a_reader() {
+        let input = "\nbyr:1\niyr:2\n\n\nbyr:3 xyz:4\n\nbyr:5";
+        let mut reader = BatchReader::new(input.as_bytes(), Mode::Strict);
+        assert_eq!(
+            Record(vec![Field::BirthYear(1), Field::IssueYear(2)]),
+            reader.next_record().unwrap().unwrap()
+        );
+        assert!(reader.next_record().unwrap().is_err());
+        assert_eq!(
+            Record(vec![Field::BirthYear(5)]),
+            reader.next_record().unwrap().unwrap()
+        );
+        assert!(reader.next_record().is_none());
+    }
+    #[test]
+    fn parses_runtime_text() {
+        let text = String::from("byr:1 pid:012345678");
+        let record = Record::parse(&text, Mode::Strict).unwrap();
+        assert_eq!(Field::PassportId("012345678"), record.fields()[1]);
+    }
+
     #[test]
     fn validates_example_part2() {
         const EXAMPLE: &str = "pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980
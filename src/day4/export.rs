@@ -0,0 +1,217 @@
+use super::{parse, Field, Mode, Record, Schema};
+use anyhow::{Context, Result};
+use std::fmt::Write;
+
+fn read_batch(s: &str, mode: Mode) -> Result<Vec<Record<'_>>> {
+    parse(s, mode)
+        .enumerate()
+        .map(|(idx, record)| record.with_context(|| format!("Record {}", idx + 1)))
+        .collect()
+}
+
+/// Where a key goes among a record's fields: in the order of the schema's rules, then the keys
+/// the schema doesn't mention, which keep their order since sorting is stable.
+fn key_order(schema: &Schema, key: &str) -> usize {
+    schema
+        .keys()
+        .position(|k| k == key)
+        .unwrap_or_else(|| schema.keys().count())
+}
+
+/// Rewrites a batch file with each record on one line, its fields in the schema's order, and
+/// a single blank line between records.
+pub fn normalise(s: &str, schema: &Schema, mode: Mode) -> Result<String> {
+    let mut out = String::new();
+    for (idx, mut record) in read_batch(s, mode)?.into_iter().enumerate() {
+        if idx > 0 {
+            out.push_str("\n\n");
+        }
+        record.0.sort_by_key(|f| key_order(schema, f.key()));
+        write!(out, "{}", record).unwrap();
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+/// The records as a JSON array of objects. Years and country IDs are numbers, and everything
+/// else is a string. A repeated field is an array of its values, so that none are lost.
+pub fn to_json(s: &str, mode: Mode) -> Result<String> {
+    let mut out = String::from("[");
+    for (idx, record) in read_batch(s, mode)?.iter().enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        out.push_str("\n  {");
+        let mut keys = Vec::new();
+        for field in &record.0 {
+            if !keys.contains(&field.key()) {
+                keys.push(field.key());
+            }
+        }
+        for (idx, key) in keys.into_iter().enumerate() {
+            if idx > 0 {
+                out.push_str(", ");
+            }
+            let values = record
+                .0
+                .iter()
+                .filter(|f| f.key() == key)
+                .map(json_value)
+                .collect::<Vec<_>>();
+            write!(out, "{}: ", json_string(key)).unwrap();
+            if let [value] = values.as_slice() {
+                out.push_str(value);
+            } else {
+                write!(out, "[{}]", values.join(", ")).unwrap();
+            }
+        }
+        out.push('}');
+    }
+    out.push_str("\n]\n");
+    Ok(out)
+}
+fn json_value(field: &Field) -> String {
+    match field {
+        Field::BirthYear(n)
+        | Field::IssueYear(n)
+        | Field::ExpirationYear(n)
+        | Field::CountryId(n) => n.to_string(),
+        _ => json_string(&field.value()),
+    }
+}
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The records as CSV, with a column for every key in the schema, then every other key that
+/// appears. Repeated fields share a cell, separated by spaces, since values never contain
+/// whitespace.
+pub fn to_csv(s: &str, schema: &Schema, mode: Mode) -> Result<String> {
+    let records = read_batch(s, mode)?;
+    let mut columns = schema.keys().collect::<Vec<_>>();
+    for field in records.iter().flat_map(|r| &r.0) {
+        if !columns.contains(&field.key()) {
+            columns.push(field.key());
+        }
+    }
+    let mut out = String::new();
+    write_csv_row(&mut out, columns.iter().map(|&key| key.to_owned()));
+    for record in &records {
+        write_csv_row(
+            &mut out,
+            columns.iter().map(|key| {
+                record
+                    .0
+                    .iter()
+                    .filter(|f| f.key() == *key)
+                    .map(Field::value)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }),
+        );
+    }
+    Ok(out)
+}
+fn write_csv_row(out: &mut String, cells: impl Iterator<Item = String>) {
+    for (idx, cell) in cells.enumerate() {
+        if idx > 0 {
+            out.push(',');
+        }
+        if cell.contains(&[',', '"'][..]) {
+            write!(out, "\"{}\"", cell.replace('"', "\"\"")).unwrap();
+        } else {
+            out.push_str(&cell);
+        }
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle_schema;
+    use super::*;
+
+    const MESSY: &str = "hgt:183cm cid:147
+ecl:gry   pid:860033327 eyr:2020 hcl:#fffffd
+byr:1937 iyr:2017\r
+
+
+iyr:2013 ecl:amb cid:350 eyr:2023 pid:028048884
+hcl:#cfa07d byr:1929 note:\"a,b\"
+";
+
+    #[test]
+    fn record_round_trips() {
+        for record in read_batch(MESSY, Mode::Lenient).unwrap() {
+            assert_eq!(
+                record,
+                Record::parse(&record.to_string(), Mode::Lenient).unwrap()
+            );
+        }
+    }
+    #[test]
+    fn normalises_batch() {
+        let schema = puzzle_schema();
+        let normalised = normalise(MESSY, &schema, Mode::Lenient).unwrap();
+        assert_eq!(
+            "byr:1937 iyr:2017 eyr:2020 hgt:183cm hcl:#fffffd ecl:gry pid:860033327 cid:147
+
+byr:1929 iyr:2013 eyr:2023 hcl:#cfa07d ecl:amb pid:028048884 cid:350 note:\"a,b\"
+",
+            normalised
+        );
+        assert_eq!(
+            normalised,
+            normalise(&normalised, &schema, Mode::Lenient).unwrap()
+        );
+        assert!(normalise(MESSY, &schema, Mode::Strict).is_err());
+        let reordered = Schema::parse("[pid]\n[byr]").unwrap();
+        assert_eq!(
+            "pid:1 byr:1990 xyz:1 hgt:170cm\n",
+            normalise("xyz:1 hgt:170cm byr:1990 pid:1", &reordered, Mode::Lenient).unwrap()
+        );
+    }
+    #[test]
+    fn exports_json() {
+        assert_eq!(
+            r##"[
+  {"hcl": "#fffffd", "byr": 1937},
+  {"note": "\"a,b\"", "cid": 350},
+  {"note": ["x", "y"], "byr": [1990, 1991]}
+]
+"##,
+            to_json(
+                "hcl:#fffffd byr:1937\n\nnote:\"a,b\" cid:350\n\nnote:x byr:1990 note:y byr:1991",
+                Mode::Lenient
+            )
+            .unwrap()
+        );
+    }
+    #[test]
+    fn exports_csv() {
+        assert_eq!(
+            r##"byr,iyr,eyr,hgt,hcl,ecl,pid,cid,note,"a,b"
+1937,,,,#fffffd,,,,,
+1929,,,183cm,,,,350,"""a,b""",
+1,,,,,,,,x y,
+,,,,,,,,,1
+"##,
+            to_csv(
+                "hcl:#fffffd byr:1937\n\nnote:\"a,b\" cid:350 hgt:183cm byr:1929\n\nbyr:1 note:x note:y\n\na,b:1",
+                &puzzle_schema(),
+                Mode::Lenient
+            )
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use super::{parse, Field, Mode, Record};
+use anyhow::{Context, Result};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+};
+
+/// Records from one or more batches, with the records for each passport ID merged into one.
+#[derive(Debug, Default, PartialEq)]
+pub struct Dedup<'a> {
+    /// A record for every passport ID whose records agree, then every record without an ID.
+    pub records: Vec<Record<'a>>,
+    /// The records for passport IDs whose records disagree, as they were read.
+    pub unmerged: Vec<Record<'a>>,
+    /// Where records for the same passport ID disagree, and so were left unmerged.
+    pub conflicts: Vec<Conflict<'a>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict<'a> {
+    pub pid: &'a str,
+    pub key: &'a str,
+    pub values: Vec<String>,
+}
+impl Display for Conflict<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {}: {} is {}",
+            self.pid,
+            self.key,
+            self.values.join(" or ")
+        )
+    }
+}
+
+pub fn dedupe_batches<'a>(batches: &[&'a str], mode: Mode) -> Result<Dedup<'a>> {
+    let mut records = Vec::new();
+    for (idx, batch) in batches.iter().enumerate() {
+        for record in parse(batch, mode) {
+            records.push(record.with_context(|| format!("Failed to read batch {}", idx + 1))?);
+        }
+    }
+    Ok(dedupe(records))
+}
+
+pub fn dedupe<'a>(records: impl IntoIterator<Item = Record<'a>>) -> Dedup<'a> {
+    let (groups, mut unidentified) = by_pid(records);
+    let mut dedup = Dedup::default();
+    for (pid, group) in groups {
+        match merge(pid, &group) {
+            Ok(record) => dedup.records.push(record),
+            Err(conflicts) => {
+                dedup.unmerged.extend(group);
+                dedup.conflicts.extend(conflicts);
+            }
+        }
+    }
+    dedup.records.append(&mut unidentified);
+    dedup
+}
+
+fn by_pid<'a>(
+    records: impl IntoIterator<Item = Record<'a>>,
+) -> (BTreeMap<&'a str, Vec<Record<'a>>>, Vec<Record<'a>>) {
+    let mut groups = BTreeMap::<_, Vec<_>>::new();
+    let mut unidentified = Vec::new();
+    for record in records {
+        match record.pid() {
+            Some(pid) => groups.entry(pid).or_default().push(record),
+            None => unidentified.push(record),
+        }
+    }
+    (groups, unidentified)
+}
+
+/// Combines records into one with every field that any of them has, unless they give a key
+/// different values.
+fn merge<'a>(pid: &'a str, records: &[Record<'a>]) -> Result<Record<'a>, Vec<Conflict<'a>>> {
+    let mut fields: Vec<Field<'a>> = Vec::new();
+    for field in records.iter().flat_map(|r| &r.0) {
+        if !fields.contains(field) {
+            fields.push(field.clone());
+        }
+    }
+    let mut seen = BTreeSet::new();
+    let conflicts = fields
+        .iter()
+        .map(Field::key)
+        .filter(|key| seen.insert(*key))
+        .filter_map(|key| {
+            let values = fields
+                .iter()
+                .filter(|f| f.key() == key)
+                .map(Field::value)
+                .collect::<Vec<_>>();
+            if values.len() > 1 {
+                Some(Conflict { pid, key, values })
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    if conflicts.is_empty() {
+        Ok(Record(fields))
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// A difference between two batches, matching records by passport ID.
+#[derive(Debug, PartialEq)]
+pub enum Change<'a> {
+    Added(Record<'a>),
+    Removed(Record<'a>),
+    Changed {
+        pid: &'a str,
+        key: &'a str,
+        before: BTreeSet<String>,
+        after: BTreeSet<String>,
+    },
+}
+impl Display for Change<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn values(values: &BTreeSet<String>) -> String {
+            if values.is_empty() {
+                "(none)".to_owned()
+            } else {
+                values.iter().cloned().collect::<Vec<_>>().join(" ")
+            }
+        }
+        match self {
+            Change::Added(record) => write!(f, "+ {}", record),
+            Change::Removed(record) => write!(f, "- {}", record),
+            Change::Changed {
+                pid,
+                key,
+                before,
+                after,
+            } => write!(
+                f,
+                "~ pid {}: {} {} -> {}",
+                pid,
+                key,
+                values(before),
+                values(after)
+            ),
+        }
+    }
+}
+
+/// What changed between two batches. Records with a passport ID are compared field by field,
+/// and records without one are either in both batches exactly or added or removed.
+pub fn diff<'a>(before: &'a str, after: &'a str, mode: Mode) -> Result<Vec<Change<'a>>> {
+    let read = |s: &'a str, which: &str| {
+        parse(s, mode)
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("Failed to read the {} batch", which))
+    };
+    let (mut before, before_unidentified) = by_pid(read(before, "earlier")?);
+    let (mut after, mut after_unidentified) = by_pid(read(after, "later")?);
+    let pids = before
+        .keys()
+        .chain(after.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    let mut changes = Vec::new();
+    for pid in pids {
+        match (before.remove(pid), after.remove(pid)) {
+            (Some(records), None) => changes.extend(records.into_iter().map(Change::Removed)),
+            (None, Some(records)) => changes.extend(records.into_iter().map(Change::Added)),
+            (Some(before), Some(after)) => {
+                let (before, after) = (values_by_key(&before), values_by_key(&after));
+                let keys = before.keys().chain(after.keys()).collect::<BTreeSet<_>>();
+                for key in keys {
+                    let values =
+                        |map: &BTreeMap<_, BTreeSet<_>>| map.get(key).cloned().unwrap_or_default();
+                    let (before, after) = (values(&before), values(&after));
+                    if before != after {
+                        changes.push(Change::Changed {
+                            pid,
+                            key,
+                            before,
+                            after,
+                        });
+                    }
+                }
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+    for record in before_unidentified {
+        match after_unidentified.iter().position(|r| *r == record) {
+            Some(idx) => {
+                after_unidentified.remove(idx);
+            }
+            None => changes.push(Change::Removed(record)),
+        }
+    }
+    changes.extend(after_unidentified.into_iter().map(Change::Added));
+    Ok(changes)
+}
+
+fn values_by_key<'a>(records: &[Record<'a>]) -> BTreeMap<&'a str, BTreeSet<String>> {
+    let mut map = BTreeMap::<_, BTreeSet<_>>::new();
+    for field in records.iter().flat_map(|r| &r.0) {
+        map.entry(field.key()).or_default().insert(field.value());
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_agreeing_records() {
+        let dedup = dedupe_batches(
+            &[
+                "pid:1 byr:1990\n\nbyr:1950",
+                "hgt:170cm pid:1 byr:1990\n\npid:2 ecl:brn",
+            ],
+            Mode::Strict,
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["pid:1 byr:1990 hgt:170cm", "pid:2 ecl:brn", "byr:1950"],
+            dedup
+                .records
+                .iter()
+                .map(Record::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert!(dedup.unmerged.is_empty());
+        assert!(dedup.conflicts.is_empty());
+    }
+    #[test]
+    fn reports_conflicts() {
+        let dedup = dedupe_batches(
+            &[
+                "pid:1 byr:1990 hgt:170cm xyz:a",
+                "pid:1 byr:1991 hgt:170cm xyz:b\n\npid:2",
+            ],
+            Mode::Lenient,
+        )
+        .unwrap();
+        assert_eq!(
+            vec!["pid 1: byr is 1990 or 1991", "pid 1: xyz is a or b"],
+            dedup
+                .conflicts
+                .iter()
+                .map(Conflict::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec![Record(vec![Field::PassportId("2")])], dedup.records);
+        assert_eq!(
+            vec![
+                "pid:1 byr:1990 hgt:170cm xyz:a",
+                "pid:1 byr:1991 hgt:170cm xyz:b"
+            ],
+            dedup
+                .unmerged
+                .iter()
+                .map(Record::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert!(dedupe_batches(&["pid:1", "xyz:1"], Mode::Strict).is_err());
+    }
+    #[test]
+    fn diffs_batches() {
+        let changes = diff(
+            "pid:1 byr:1990 hgt:170cm\n\npid:2 byr:1980\n\nbyr:1950\n\nbyr:1960",
+            "pid:1 byr:1991 ecl:brn hgt:170cm\n\npid:3 byr:1970\n\nbyr:1960\n\nbyr:1940",
+            Mode::Strict,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                "~ pid 1: byr 1990 -> 1991",
+                "~ pid 1: ecl (none) -> brn",
+                "- pid:2 byr:1980",
+                "+ pid:3 byr:1970",
+                "- byr:1950",
+                "+ byr:1940",
+            ],
+            changes.iter().map(Change::to_string).collect::<Vec<_>>()
+        );
+        assert!(diff("pid:1", "pid:1", Mode::Strict).unwrap().is_empty());
+    }
+}
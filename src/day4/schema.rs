@@ -0,0 +1,446 @@
+use super::{Length, Unit};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use std::{ops::RangeInclusive, path::Path};
+
+/// The rules a passport's fields must follow, in the format of `data/day4-schema.txt`.
+#[derive(Debug, PartialEq)]
+pub struct Schema {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Rule {
+    pub key: String,
+    pub required: bool,
+    pub unitless: Unitless,
+    pub checks: Vec<Check>,
+}
+
+/// What to make of a bare number where the rule's checks expect a unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unitless {
+    Reject,
+    Assume(Unit),
+    /// Accept the value if it would pass in any unit.
+    Either,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Check {
+    Range(RangeInclusive<i64>),
+    Units(Vec<(String, RangeInclusive<i64>)>),
+    /// A height between two lengths, in whichever unit, comparing across units.
+    Height(Length, Length),
+    OneOf(Vec<String>),
+    Pattern(Pattern),
+}
+
+/// A fixed-length sequence of characters or character classes.
+#[derive(Debug, PartialEq)]
+pub struct Pattern {
+    source: String,
+    atoms: Vec<Atom>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Atom {
+    Digit,
+    HexDigit,
+    Letter,
+    Literal(char),
+}
+
+impl Schema {
+    pub fn parse(s: &str) -> Result<Schema> {
+        let mut rules: Vec<Rule> = Vec::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let ctx = || format!("Schema line {}: {}", idx + 1, line);
+            if line.starts_with('[') && line.ends_with(']') {
+                let key = line[1..line.len() - 1].trim();
+                ensure!(
+                    !rules.iter().any(|r| r.key == key),
+                    "{}: {} is already defined",
+                    ctx(),
+                    key
+                );
+                rules.push(Rule {
+                    key: key.to_owned(),
+                    required: true,
+                    unitless: Unitless::Reject,
+                    checks: Vec::new(),
+                });
+            } else {
+                let rule = rules
+                    .last_mut()
+                    .ok_or_else(|| anyhow!("Expected a [key] section"))
+                    .with_context(ctx)?;
+                rule.parse_setting(line).with_context(ctx)?;
+            }
+        }
+        for rule in &rules {
+            ensure!(
+                rule.unitless == Unitless::Reject
+                    || rule
+                        .checks
+                        .iter()
+                        .any(|c| matches!(c, Check::Units(_) | Check::Height(_, _))),
+                "[{}]: unitless needs a units or height check",
+                rule.key
+            );
+        }
+        Ok(Schema { rules })
+    }
+    pub fn load(path: impl AsRef<Path>) -> Result<Schema> {
+        let path = path.as_ref();
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Schema::parse(&s)
+    }
+    pub fn rule(&self, key: &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.key == key)
+    }
+    /// Every key with a rule, in the order the schema lists them.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|r| r.key.as_str())
+    }
+    pub fn required_keys(&self) -> impl Iterator<Item = &str> {
+        self.rules
+            .iter()
+            .filter(|r| r.required)
+            .map(|r| r.key.as_str())
+    }
+}
+
+impl Rule {
+    fn parse_setting(&mut self, line: &str) -> Result<()> {
+        let (name, value) = crate::util::split_tuple_2(line, "=")
+            .ok_or_else(|| anyhow!("Expected name = value"))?;
+        let value = value.trim();
+        match name.trim() {
+            "required" => {
+                self.required = value.parse()?;
+            }
+            "range" => self.checks.push(Check::Range(parse_range(value)?)),
+            "units" => self.checks.push(Check::Units(
+                value
+                    .split(',')
+                    .map(|unit| {
+                        let mut tokens = unit.split_whitespace();
+                        match (tokens.next(), tokens.next(), tokens.next()) {
+                            (Some(unit), Some(range), None) => {
+                                Ok((unit.to_owned(), parse_range(range)?))
+                            }
+                            _ => bail!("Expected a unit and a range, not {}", unit.trim()),
+                        }
+                    })
+                    .collect::<Result<_>>()?,
+            )),
+            "height" => {
+                let (min, max) = crate::util::split_tuple_2(value, "-")
+                    .ok_or_else(|| anyhow!("Invalid height range {}", value))?;
+                let parse = |s: &str| -> Result<Length> {
+                    let length = s.trim().parse::<Length>()?;
+                    ensure!(length.micrometres().is_some(), "{} needs a unit", s.trim());
+                    Ok(length)
+                };
+                self.checks.push(Check::Height(parse(min)?, parse(max)?))
+            }
+            "unitless" => {
+                self.unitless = match value {
+                    "reject" => Unitless::Reject,
+                    "cm" => Unitless::Assume(Unit::Centimetre),
+                    "in" => Unitless::Assume(Unit::Inch),
+                    "either" => Unitless::Either,
+                    _ => bail!(
+                        "Unitless values are reject, cm, in or either, not {}",
+                        value
+                    ),
+                }
+            }
+            "one of" => self.checks.push(Check::OneOf(
+                value.split_whitespace().map(str::to_owned).collect(),
+            )),
+            "pattern" => self.checks.push(Check::Pattern(value.parse()?)),
+            other => bail!("Unknown setting {}", other),
+        }
+        Ok(())
+    }
+    /// Why the value breaks this rule, if it does.
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let check = |value: &str| self.checks.iter().try_for_each(|c| c.check(value));
+        let with_unit = |unit: Unit| check(&format!("{}{}", value, unit.suffix()));
+        if value.parse::<i64>().is_err() {
+            return check(value);
+        }
+        match self.unitless {
+            Unitless::Reject => check(value),
+            Unitless::Assume(unit) => with_unit(unit),
+            Unitless::Either => with_unit(Unit::Centimetre)
+                .or_else(|cm| with_unit(Unit::Inch).map_err(|inch| format!("{} or {}", cm, inch))),
+        }
+    }
+    pub fn accepts(&self, value: &str) -> bool {
+        self.check(value).is_ok()
+    }
+}
+
+fn parse_range(s: &str) -> Result<RangeInclusive<i64>> {
+    let (lower, upper) =
+        crate::util::split_tuple_2(s, "-").ok_or_else(|| anyhow!("Invalid range {}", s))?;
+    Ok(lower.trim().parse()?..=upper.trim().parse()?)
+}
+
+impl Check {
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        fn in_range(n: &str, range: &RangeInclusive<i64>, unit: &str) -> Result<(), String> {
+            match n.parse::<i64>() {
+                Ok(n) if range.contains(&n) => Ok(()),
+                Ok(_) => Err(format!("outside {}–{}{}", range.start(), range.end(), unit)),
+                Err(_) => Err("is not a number".to_owned()),
+            }
+        }
+        match self {
+            Check::Range(range) => in_range(value, range, ""),
+            Check::Units(units) => match units
+                .iter()
+                .find_map(|(unit, range)| Some((value.strip_suffix(unit.as_str())?, unit, range)))
+            {
+                Some((n, unit, range)) => in_range(n, range, unit),
+                None => Err(format!(
+                    "needs a unit of {}",
+                    units
+                        .iter()
+                        .map(|(unit, _)| unit.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" or ")
+                )),
+            },
+            Check::Height(min, max) => match value.parse::<Length>().map(Length::micrometres) {
+                Ok(Some(um))
+                    if um >= min.micrometres().unwrap() && um <= max.micrometres().unwrap() =>
+                {
+                    Ok(())
+                }
+                Ok(Some(_)) => Err(format!("outside {}–{}", min, max)),
+                Ok(None) => Err("has no unit".to_owned()),
+                Err(_) => Err("is not a height".to_owned()),
+            },
+            Check::OneOf(values) => {
+                if values.iter().any(|v| v == value) {
+                    Ok(())
+                } else {
+                    Err(format!("is not one of {}", values.join(", ")))
+                }
+            }
+            Check::Pattern(pattern) => {
+                if pattern.matches(value) {
+                    Ok(())
+                } else {
+                    Err(format!("does not match {}", pattern.source))
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Pattern {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut atoms = Vec::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => atoms.push(match chars.next() {
+                    Some('d') => Atom::Digit,
+                    Some('h') => Atom::HexDigit,
+                    Some('a') => Atom::Letter,
+                    Some(other) => bail!("Unknown character class \\{}", other),
+                    None => bail!("Pattern ends with \\"),
+                }),
+                '{' => {
+                    let mut count = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => count.push(c),
+                            None => bail!("Unclosed {{ in {}", s),
+                        }
+                    }
+                    let count = count.parse::<usize>()?;
+                    let atom = *atoms
+                        .last()
+                        .ok_or_else(|| anyhow!("Nothing to repeat in {}", s))?;
+                    ensure!(count > 0, "Cannot repeat zero times in {}", s);
+                    atoms.extend(std::iter::repeat_n(atom, count - 1));
+                }
+                c => atoms.push(Atom::Literal(c)),
+            }
+        }
+        Ok(Pattern {
+            source: s.to_owned(),
+            atoms,
+        })
+    }
+}
+impl Pattern {
+    pub fn matches(&self, s: &str) -> bool {
+        let mut chars = s.chars();
+        self.atoms.iter().all(|atom| {
+            chars.next().is_some_and(|c| match atom {
+                Atom::Digit => c.is_ascii_digit(),
+                Atom::HexDigit => c.is_ascii_hexdigit(),
+                Atom::Letter => c.is_alphabetic(),
+                Atom::Literal(l) => c == *l,
+            })
+        }) && chars.next().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_schema() {
+        let schema = Schema::parse(
+            "# comment
+[hgt]
+units = cm 150-193, in 59-76
+
+[cid]
+required = false
+one of = a b",
+        )
+        .unwrap();
+        assert_eq!(
+            Schema {
+                rules: vec![
+                    Rule {
+                        key: "hgt".to_owned(),
+                        required: true,
+                        unitless: Unitless::Reject,
+                        checks: vec![Check::Units(vec![
+                            ("cm".to_owned(), 150..=193),
+                            ("in".to_owned(), 59..=76)
+                        ])]
+                    },
+                    Rule {
+                        key: "cid".to_owned(),
+                        required: false,
+                        unitless: Unitless::Reject,
+                        checks: vec![Check::OneOf(vec!["a".to_owned(), "b".to_owned()])]
+                    }
+                ]
+            },
+            schema
+        );
+        assert_eq!(vec!["hgt"], schema.required_keys().collect::<Vec<_>>());
+    }
+    #[test]
+    fn rejects_bad_schemas() {
+        assert!(Schema::parse("range = 1-2").is_err());
+        assert!(Schema::parse("[a]\n[a]").is_err());
+        assert!(Schema::parse("[a]\nsize = 3").is_err());
+        assert!(Schema::parse("[a]\nrange = 3").is_err());
+        assert!(Schema::parse("[a]\nunits = cm").is_err());
+        assert!(Schema::parse("[a]\npattern = \\x").is_err());
+        assert!(Schema::parse("[a]\npattern = {3}").is_err());
+        assert!(Schema::parse("[a]\npattern = #\\h{6").is_err());
+        assert!(Schema::parse("[byr]\nrange = 1920-2002\nunitless = cm").is_err());
+        assert!(Schema::parse("[hgt]\nunitless = cm\nunits = cm 150-193").is_ok());
+    }
+    #[test]
+    fn loads_puzzle_schema() {
+        let schema = Schema::load("src/data/day4-schema.txt").unwrap();
+        assert_eq!(
+            vec!["byr", "iyr", "eyr", "hgt", "hcl", "ecl", "pid"],
+            schema.required_keys().collect::<Vec<_>>()
+        );
+        assert!(!schema.rule("cid").unwrap().required);
+    }
+    #[test]
+    fn checks_values() {
+        let hgt = Check::Units(vec![("cm".to_owned(), 150..=193)]);
+        assert!(hgt.check("150cm").is_ok());
+        assert!(hgt.check("194cm").is_err());
+        assert!(hgt.check("150").is_err());
+        assert!(Check::Range(1..=2).check("2").is_ok());
+        assert!(Check::Range(1..=2).check("two").is_err());
+        let hcl = "#\\h{6}".parse::<Pattern>().unwrap();
+        assert!(hcl.matches("#123abc"));
+        assert!(!hcl.matches("#123abz"));
+        assert!(!hcl.matches("123abc"));
+        assert!(!hcl.matches("#123abc0"));
+        assert!(!hcl.matches("#123ab"));
+    }
+    #[test]
+    fn explains_failures() {
+        let hgt = Check::Units(vec![
+            ("cm".to_owned(), 150..=193),
+            ("in".to_owned(), 59..=76),
+        ]);
+        assert_eq!(Err("outside 150–193cm".to_owned()), hgt.check("59cm"));
+        assert_eq!(Err("needs a unit of cm or in".to_owned()), hgt.check("59"));
+        assert_eq!(Err("is not a number".to_owned()), hgt.check("tallin"));
+        assert_eq!(
+            Err("outside 1920–2002".to_owned()),
+            Check::Range(1920..=2002).check("2003")
+        );
+        assert_eq!(
+            Err("is not one of amb, blu".to_owned()),
+            Check::OneOf(vec!["amb".to_owned(), "blu".to_owned()]).check("zzz")
+        );
+        assert_eq!(
+            Err("does not match \\d{9}".to_owned()),
+            Check::Pattern("\\d{9}".parse().unwrap()).check("123")
+        );
+    }
+    #[test]
+    fn compares_heights_across_units() {
+        let schema = Schema::parse("[hgt]\nheight = 150cm-76in").unwrap();
+        let rule = schema.rule("hgt").unwrap();
+        assert_eq!(
+            Check::Height(Length::Centimetre(150), Length::Inch(76)),
+            rule.checks[0]
+        );
+        assert!(rule.check("150cm").is_ok());
+        assert!(rule.check("60in").is_ok());
+        assert!(rule.check("193cm").is_ok());
+        assert_eq!(Err("outside 150cm–76in".to_owned()), rule.check("59in"));
+        assert_eq!(Err("outside 150cm–76in".to_owned()), rule.check("194cm"));
+        assert_eq!(Err("has no unit".to_owned()), rule.check("170"));
+        assert_eq!(Err("is not a height".to_owned()), rule.check("tall"));
+        assert!(Schema::parse("[hgt]\nheight = 150-193cm").is_err());
+    }
+    #[test]
+    fn tolerates_unitless_values() {
+        let rule = |unitless: &str| {
+            Schema::parse(&format!(
+                "[hgt]\nunits = cm 150-193, in 59-76\nunitless = {}",
+                unitless
+            ))
+            .unwrap()
+            .rules
+            .remove(0)
+        };
+        assert_eq!(
+            Err("needs a unit of cm or in".to_owned()),
+            rule("reject").check("170")
+        );
+        assert!(rule("cm").check("170").is_ok());
+        assert_eq!(Err("outside 59–76in".to_owned()), rule("in").check("170"));
+        assert!(rule("either").check("170").is_ok());
+        assert!(rule("either").check("70").is_ok());
+        assert_eq!(
+            Err("outside 150–193cm or outside 59–76in".to_owned()),
+            rule("either").check("100")
+        );
+        assert!(rule("in").check("170cm").is_ok());
+        assert!(Schema::parse("[hgt]\nunitless = mm").is_err());
+    }
+}
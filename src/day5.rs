@@ -1,27 +1,15 @@
-use anyhow::{bail, Context, Error};
+use anyhow::{bail, ensure, Context, Error, Result};
 use std::str::FromStr;
 
 pub(crate) fn part1(s: &str) -> usize {
-    s.lines()
-        .map(|l| {
-            l.parse::<Position>()
-                .with_context(|| format!("Could not parse {}", l))
-                .unwrap()
-                .get_id()
-        })
+    seat_ids(s, &Layout::default())
+        .unwrap()
+        .into_iter()
         .max()
         .expect("No lines")
 }
 pub(crate) fn part2(s: &str) -> usize {
-    let mut seats = s
-        .lines()
-        .map(|l| {
-            l.parse::<Position>()
-                .with_context(|| format!("Could not parse {}", l))
-                .unwrap()
-                .get_id()
-        })
-        .collect::<Vec<_>>();
+    let mut seats = seat_ids(s, &Layout::default()).unwrap();
     seats.sort();
     let mut cmp = seats[0];
     for &p in seats[1..].into_iter() {
@@ -36,54 +24,127 @@ pub(crate) fn part2(s: &str) -> usize {
     panic!("Failed to find a missing sequence")
 }
 
-struct Position([u8; 10]);
+/// The seat ID for every boarding pass in `s`, one per line.
+pub fn seat_ids(s: &str, layout: &Layout) -> Result<Vec<usize>> {
+    s.lines()
+        .map(|l| {
+            layout
+                .decode(l)
+                .with_context(|| format!("Could not parse {}", l))
+                .map(|p| layout.get_id(&p))
+        })
+        .collect()
+}
 
-const FRONT: u8 = b'F';
-const BACK: u8 = b'B';
-const LEFT: u8 = b'L';
-const RIGHT: u8 = b'R';
+/// How boarding passes are laid out: a number of row letters, then a number of column letters,
+/// each picking the lower or upper half of what remains.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    row_bits: u32,
+    col_bits: u32,
+    front: char,
+    back: char,
+    left: char,
+    right: char,
+}
 
-impl FromStr for Position {
-    type Err = Error;
+impl Default for Layout {
+    /// 128 rows of 8 seats, as F/B then L/R.
+    fn default() -> Self {
+        Layout {
+            row_bits: 7,
+            col_bits: 3,
+            front: 'F',
+            back: 'B',
+            left: 'L',
+            right: 'R',
+        }
+    }
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let b = s.as_bytes();
-        if b.len() != 10 {
+impl Layout {
+    /// A plane with `2^row_bits` rows of `2^col_bits` seats, using the usual letters.
+    pub fn new(row_bits: u32, col_bits: u32) -> Result<Self> {
+        ensure!(
+            row_bits
+                .checked_add(col_bits)
+                .is_some_and(|bits| bits <= usize::BITS),
+            "{} row bits and {} column bits don't fit in a {}-bit seat ID",
+            row_bits,
+            col_bits,
+            usize::BITS
+        );
+        Ok(Layout {
+            row_bits,
+            col_bits,
+            ..Layout::default()
+        })
+    }
+    /// Uses `front`/`back` for the lower/upper half of the rows, and `left`/`right` for the
+    /// columns.
+    pub fn with_letters(self, [front, back]: [char; 2], [left, right]: [char; 2]) -> Result<Self> {
+        ensure!(front != back, "Both halves of the rows are '{}'", front);
+        ensure!(left != right, "Both halves of the columns are '{}'", left);
+        Ok(Layout {
+            front,
+            back,
+            left,
+            right,
+            ..self
+        })
+    }
+    pub fn decode(&self, s: &str) -> Result<Position> {
+        let len = s.chars().count();
+        if len != (self.row_bits + self.col_bits) as usize {
             bail!("Wrong length")
         }
-        for &v in &b[..7] {
-            if v != BACK && v != FRONT {
-                bail!("Invalid front-back {}", v)
-            }
-        }
-        for &v in &b[7..] {
-            if v != LEFT && v != RIGHT {
-                bail!("Invalid left-right {}", v)
+        let mut position = Position { row: 0, col: 0 };
+        for (idx, c) in s.chars().enumerate() {
+            if idx < self.row_bits as usize {
+                let bit = match c {
+                    c if c == self.front => 0,
+                    c if c == self.back => 1,
+                    _ => bail!("Invalid front-back {}", c),
+                };
+                position.row = (position.row << 1) | bit;
+            } else {
+                let bit = match c {
+                    c if c == self.left => 0,
+                    c if c == self.right => 1,
+                    _ => bail!("Invalid left-right {}", c),
+                };
+                position.col = (position.col << 1) | bit;
             }
         }
-        // MaybeUninit is hard, let's just overwrite 10 bytes.
-        let mut output = [0u8; 10];
-        output.copy_from_slice(b);
-        Ok(Position(output))
+        Ok(position)
+    }
+    pub fn get_id(&self, position: &Position) -> usize {
+        get_id_from_index(position.get_index(), self.col_bits)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    row: usize,
+    col: usize,
+}
+
+impl FromStr for Position {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Layout::default().decode(s)
     }
 }
 
-fn get_id_from_index(index: (usize, usize)) -> usize {
-    index.0 * 8 + index.1
+fn get_id_from_index(index: (usize, usize), col_bits: u32) -> usize {
+    // Shifting by the full width of usize overflows, although the row is then always 0.
+    index.0.checked_shl(col_bits).unwrap_or(0) | index.1
 }
 
 impl Position {
     fn get_index(&self) -> (usize, usize) {
-        self.0.iter().fold((0, 0), |(row, col), &cur| match cur {
-            FRONT => (row << 1, col),
-            BACK => (1 + (row << 1), col),
-            LEFT => (row, col << 1),
-            RIGHT => (row, 1 + (col << 1)),
-            _ => unreachable!(),
-        })
-    }
-    fn get_id(&self) -> usize {
-        get_id_from_index(self.get_index())
+        (self.row, self.col)
     }
 }
 
@@ -93,7 +154,10 @@ mod tests {
 
     #[test]
     fn can_parse() {
-        assert!("FBFBBFFRLR".parse::<Position>().unwrap().0.len() == 10)
+        assert!("FBFBBFFRLR".parse::<Position>().is_ok());
+        assert!("FBFBBFFRL".parse::<Position>().is_err());
+        assert!("FBFBBFFRLB".parse::<Position>().is_err());
+        assert!("FBFBBFLRLR".parse::<Position>().is_err());
     }
     #[test]
     fn can_get_zero_index() {
@@ -130,9 +194,37 @@ mod tests {
     }
     #[test]
     fn can_get_id() {
-        assert_eq!(get_id_from_index((44, 5)), 357);
-        assert_eq!(get_id_from_index((70, 7)), 567);
-        assert_eq!(get_id_from_index((14, 7)), 119);
-        assert_eq!(get_id_from_index((102, 4)), 820);
+        assert_eq!(get_id_from_index((44, 5), 3), 357);
+        assert_eq!(get_id_from_index((70, 7), 3), 567);
+        assert_eq!(get_id_from_index((14, 7), 3), 119);
+        assert_eq!(get_id_from_index((102, 4), 3), 820);
+    }
+    #[test]
+    fn can_use_other_layouts() {
+        let layout = Layout::new(3, 2)
+            .unwrap()
+            .with_letters(['U', 'D'], ['<', '>'])
+            .unwrap();
+        let position = layout.decode("DUD><").unwrap();
+        assert_eq!((5, 2), position.get_index());
+        assert_eq!(22, layout.get_id(&position));
+        assert!(layout.decode("FBF><").is_err());
+        assert!(layout.decode("DUDLR").is_err());
+        assert_eq!(vec![0, 31], seat_ids("UUU<<\nDDD>>", &layout).unwrap());
+        assert!(Layout::default()
+            .with_letters(['F', 'F'], ['L', 'R'])
+            .is_err());
+    }
+    #[test]
+    fn wide_layouts_do_not_overflow() {
+        let layout = Layout::new(40, 24).unwrap();
+        let last = "B".repeat(40) + &"R".repeat(24);
+        assert_eq!(usize::MAX, layout.get_id(&layout.decode(&last).unwrap()));
+        let layout = Layout::new(0, 64).unwrap();
+        assert_eq!(
+            usize::MAX,
+            layout.get_id(&layout.decode(&"R".repeat(64)).unwrap())
+        );
+        assert!(Layout::new(60, 5).is_err());
     }
 }
@@ -1,4 +1,10 @@
+pub mod automaton;
 pub mod circular_buffer;
+pub mod grid;
+pub mod modular;
+
+use anyhow::{anyhow, bail, Result};
+use std::fmt::Debug;
 
 pub fn split_tuple_2<'source, 'pattern>(
     s: &'source str,
@@ -22,6 +28,59 @@ pub fn tuples<T>(items: &[T]) -> impl Iterator<Item = (&T, &T)> {
     }).flatten()
 }
 
+/// Takes a vector of alternative-options, and returns a vector containing the single solution
+pub fn flat_dedup<T: PartialEq + Debug + ?Sized>(mut vec: Vec<Vec<&T>>) -> Result<Vec<&T>> {
+    /// Deduplicates options from the source multivec if they are unique in the dest single-vec,
+    /// returning `true` if at least one element could be transferred
+    fn transfer<'a, U: PartialEq + Debug + ?Sized>(
+        source: &mut Vec<Vec<&'a U>>,
+        dest: &mut Vec<Option<&'a U>>,
+    ) -> Result<bool> {
+        for unavailable in dest.iter().filter_map(|&i| i) {
+            for options in source.iter_mut() {
+                options.retain(|&el| el != unavailable);
+            }
+        }
+        let mut did_thing = false;
+        for (idx, single) in source.iter_mut().enumerate().filter(|(_, v)| v.len() == 1) {
+            let dest_cell = dest.get_mut(idx).unwrap();
+            let val = single.remove(0);
+            if let Some(existing) = dest_cell.replace(val) {
+                bail!(
+                    "Unexpectedly replaced an existing unique solution at index {} ({:?}→{:?})",
+                    idx,
+                    existing,
+                    dest_cell
+                );
+            } else {
+                did_thing = true;
+            }
+        }
+        Ok(did_thing)
+    }
+    let mut out: Vec<Option<&T>> = std::iter::repeat_with(|| None).take(vec.len()).collect();
+    for (idx, items) in vec.iter_mut().enumerate() {
+        if items.len() == 1 {
+            out[idx] = Some(items.remove(0));
+        }
+    }
+    let mut cycles = 0;
+    while transfer(&mut vec, &mut out)? {
+        cycles += 1;
+    }
+    out.into_iter()
+        .enumerate()
+        .map(|(idx, el)| {
+            el.ok_or(anyhow!(
+                "Could not resolve duplicates at index {} after {} cycles; {:?}",
+                idx,
+                cycles,
+                vec[idx],
+            ))
+        })
+        .collect::<Result<Vec<_>>>()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -38,6 +97,16 @@ mod tests {
         assert_eq!(None, split_tuple_2("nooooope", "oops"))
     }
 
+    #[test]
+    fn can_flat_dedup() {
+        let options = vec![vec!["a", "b"], vec!["a"], vec!["a", "b", "c"]];
+        assert_eq!(vec!["b", "a", "c"], flat_dedup::<str>(options).unwrap());
+    }
+    #[test]
+    fn flat_dedup_fails_when_ambiguous() {
+        assert!(flat_dedup::<i32>(vec![vec![&1, &2], vec![&1, &2]]).is_err());
+    }
+
     #[test]
     fn can_tuple() {
         let items = [1i32, 2, 3];
@@ -0,0 +1,72 @@
+/// Two copies of an automaton's state, which take turns being the source and the destination
+/// of each step.
+pub struct Generations<T> {
+    count: usize,
+    even: T,
+    odd: T,
+}
+
+impl<T: Clone> Generations<T> {
+    pub fn from_initial(initial: T) -> Generations<T> {
+        Generations {
+            count: 0,
+            odd: initial.clone(),
+            even: initial,
+        }
+    }
+}
+impl<T> Generations<T> {
+    pub fn current(&self) -> &T {
+        if self.count.is_multiple_of(2) {
+            &self.even
+        } else {
+            &self.odd
+        }
+    }
+    /// Runs `f` to write the next generation over the previous one.
+    pub fn step(&mut self, f: impl FnOnce(&T, &mut T)) {
+        let (active, next) = if self.count.is_multiple_of(2) {
+            (&self.even, &mut self.odd)
+        } else {
+            (&self.odd, &mut self.even)
+        };
+        f(active, next);
+        self.count += 1;
+    }
+    pub fn step_count(&self) -> usize {
+        self.count
+    }
+}
+impl<T: PartialEq> Generations<T> {
+    pub fn is_stable(&self) -> bool {
+        self.count > 0 && self.even == self.odd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn increment_to_three(source: &i32, dest: &mut i32) {
+        *dest = (*source + 1).min(3);
+    }
+
+    #[test]
+    fn can_step() {
+        let mut g = Generations::from_initial(0);
+        g.step(increment_to_three);
+        g.step(increment_to_three);
+        assert_eq!(2, *g.current());
+        assert_eq!(2, g.step_count());
+    }
+    #[test]
+    fn becomes_stable() {
+        let mut g = Generations::from_initial(0);
+        assert!(!g.is_stable());
+        while !g.is_stable() {
+            g.step(increment_to_three);
+        }
+        assert_eq!(3, *g.current());
+        assert_eq!(4, g.step_count());
+    }
+}
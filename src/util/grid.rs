@@ -0,0 +1,260 @@
+use anyhow::{bail, ensure, Error, Result};
+use std::{
+    convert::TryFrom,
+    fmt::{Debug, Display},
+    ops::{Index, IndexMut},
+};
+
+/// A dense, rectangular, row-major grid of cells, indexed by `(x, y)` where `x` is the column
+/// and `y` is the row.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+/// One of the eight symmetries of a square: an optional mirror about the vertical axis,
+/// followed by some number of clockwise quarter-turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Orientation {
+    pub flipped: bool,
+    pub quarter_turns: u8,
+}
+
+impl Orientation {
+    pub fn all() -> impl Iterator<Item = Orientation> {
+        [false, true].iter().flat_map(|&flipped| {
+            (0..4).map(move |quarter_turns| Orientation {
+                flipped,
+                quarter_turns,
+            })
+        })
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_cells(width: usize, height: usize, cells: Vec<T>) -> Result<Grid<T>> {
+        ensure!(
+            width * height == cells.len(),
+            "Expected {}x{} cells, got {}",
+            width,
+            height,
+            cells.len()
+        );
+        Ok(Grid {
+            width,
+            height,
+            cells,
+        })
+    }
+    pub fn width(&self) -> usize {
+        self.width
+    }
+    pub fn height(&self) -> usize {
+        self.height
+    }
+    pub fn get(&self, x: usize, y: usize) -> Option<&T> {
+        if x < self.width && y < self.height {
+            Some(&self.cells[y * self.width + x])
+        } else {
+            None
+        }
+    }
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        self.cells.iter().skip(x).step_by(self.width.max(1))
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.cells.iter()
+    }
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+}
+
+impl<T> Grid<T>
+where
+    T: TryFrom<u8>,
+    Error: From<T::Error>,
+{
+    /// Parses one cell per byte, one row per line; all rows must be the same width.
+    pub fn parse(s: &str) -> Result<Grid<T>> {
+        let mut width = None;
+        let mut cells = Vec::new();
+        for (row, line) in s.lines().enumerate() {
+            let expected = *width.get_or_insert(line.len());
+            if line.len() != expected {
+                bail!(
+                    "Row {} has width {}, expected {}",
+                    row,
+                    line.len(),
+                    expected
+                )
+            }
+            for &b in line.as_bytes() {
+                cells.push(T::try_from(b)?);
+            }
+        }
+        let width = width.unwrap_or(0);
+        let height = cells.len().checked_div(width).unwrap_or(0);
+        Grid::from_cells(width, height, cells)
+    }
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn filled(width: usize, height: usize, value: T) -> Grid<T> {
+        Grid {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+    /// Copies the `width`×`height` region whose top-left corner is at `(x, y)`.
+    pub fn sub_grid(&self, x: usize, y: usize, width: usize, height: usize) -> Grid<T> {
+        let cells = (y..y + height)
+            .flat_map(|row| self.row(row)[x..x + width].iter().cloned())
+            .collect();
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+    pub fn flipped(&self) -> Grid<T> {
+        let cells = self
+            .rows()
+            .flat_map(|row| row.iter().rev().cloned())
+            .collect();
+        Grid { cells, ..*self }
+    }
+    pub fn rotated_clockwise(&self) -> Grid<T> {
+        // the new row y is the old column y, read bottom-to-top
+        let cells = (0..self.width)
+            .flat_map(|x| (0..self.height).rev().map(move |y| (x, y)))
+            .map(|(x, y)| self[(x, y)].clone())
+            .collect();
+        Grid {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+    pub fn oriented(&self, orientation: Orientation) -> Grid<T> {
+        let mut output = if orientation.flipped {
+            self.flipped()
+        } else {
+            self.clone()
+        };
+        for _ in 0..orientation.quarter_turns % 4 {
+            output = output.rotated_clockwise();
+        }
+        output
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, (x, y): (usize, usize)) -> &Self::Output {
+        assert!(x < self.width, "Column {} out of range", x);
+        &self.cells[y * self.width + x]
+    }
+}
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, (x, y): (usize, usize)) -> &mut Self::Output {
+        assert!(x < self.width, "Column {} out of range", x);
+        &mut self.cells[y * self.width + x]
+    }
+}
+
+impl<T: Display> Display for Grid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (idx, row) in self.rows().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            for cell in row {
+                write!(f, "{}", cell)?;
+            }
+        }
+        Ok(())
+    }
+}
+impl<T> Debug for Grid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Grid ({}x{})", self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    struct Cell(u8);
+    impl TryFrom<u8> for Cell {
+        type Error = Error;
+
+        fn try_from(value: u8) -> Result<Self, Self::Error> {
+            if value.is_ascii_alphanumeric() {
+                Ok(Cell(value))
+            } else {
+                bail!("Unexpected token {}", value)
+            }
+        }
+    }
+    impl Display for Cell {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{}", self.0 as char)
+        }
+    }
+    const EXAMPLE: &str = "abc
+def";
+
+    #[test]
+    fn can_parse() {
+        let g = Grid::<Cell>::parse(EXAMPLE).unwrap();
+        assert_eq!((3, 2), (g.width(), g.height()));
+        assert_eq!(Cell(b'f'), g[(2, 1)]);
+        assert_eq!(None, g.get(3, 0));
+    }
+    #[test]
+    fn parse_rejects_ragged_rows() {
+        assert!(Grid::<Cell>::parse("abc\nde").is_err());
+    }
+    #[test]
+    fn parse_rejects_bad_cells() {
+        assert!(Grid::<Cell>::parse("ab!").is_err());
+    }
+    #[test]
+    fn can_flip() {
+        let g = Grid::<Cell>::parse(EXAMPLE).unwrap();
+        assert_eq!("cba\nfed", g.flipped().to_string());
+    }
+    #[test]
+    fn can_rotate() {
+        let g = Grid::<Cell>::parse(EXAMPLE).unwrap();
+        assert_eq!("da\neb\nfc", g.rotated_clockwise().to_string());
+    }
+    #[test]
+    fn all_orientations_are_distinct() {
+        let g = Grid::<Cell>::parse("ab\ncd").unwrap();
+        let all = Orientation::all()
+            .map(|o| g.oriented(o).to_string())
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(8, all.len());
+    }
+    #[test]
+    fn can_get_sub_grid() {
+        let g = Grid::<Cell>::parse(EXAMPLE).unwrap();
+        assert_eq!("e", g.sub_grid(1, 1, 1, 1).to_string());
+        assert_eq!("ad", g.column(0).map(|c| c.to_string()).collect::<String>());
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+
+pub fn bezout_identity(a: i128, b: i128) -> (i128, i128) {
+    let mut r = (a, b);
+    let mut s = (1, 0);
+    let mut t = (0, 1);
+    fn mutate(x: &mut (i128, i128), quotient: i128) {
+        *x = (x.1, x.0 - quotient * x.1);
+    }
+    loop {
+        if r.1 == 0 {
+            return (s.0, t.0);
+        }
+        let q = r.0 / r.1;
+        mutate(&mut r, q);
+        mutate(&mut s, q);
+        mutate(&mut t, q);
+    }
+}
+
+/// A congruence `x ≡ divisor (mod modulus)`.
+#[derive(Clone, Copy)]
+pub struct Equation {
+    pub divisor: i128,
+    pub modulus: i128,
+}
+impl Equation {
+    pub fn new(divisor: i128, modulus: i128) -> Equation {
+        let divisor = divisor % modulus;
+        Equation {
+            divisor: if divisor < 0 {
+                divisor + modulus
+            } else {
+                divisor
+            },
+            modulus,
+        }
+    }
+    pub fn reduce(u: &Equation, v: &Equation) -> Equation {
+        let &Equation {
+            divisor: a1,
+            modulus: n1,
+        } = u;
+        let &Equation {
+            divisor: a2,
+            modulus: n2,
+        } = v;
+        let (m1, m2) = bezout_identity(n1, n2);
+        debug_assert!(m1 * n1 + m2 * n2 == 1);
+        let x = a1
+            .checked_mul(m2)
+            .and_then(|u| u.checked_mul(n2))
+            .unwrap_or_else(|| panic!("Bad with {}, {}, {}", a1, m2, n2))
+            + a2.checked_mul(m1)
+                .and_then(|u| u.checked_mul(n1))
+                .unwrap_or_else(|| panic!("Bad with {} + {} * {} * {}", a1 * m2 * n2, a2, m1, n1));
+        Equation::new(
+            x,
+            n1.checked_mul(n2)
+                .unwrap_or_else(|| panic!("Bad with {}, {}", n1, n2)),
+        )
+    }
+    pub fn solve_set(set: impl Iterator<Item = Equation>) -> i128 {
+        set.fold(None, |prev, cur| {
+            if let Some(prev) = prev {
+                Some(Equation::reduce(&cur, &prev))
+            } else {
+                Some(cur)
+            }
+        })
+        .unwrap()
+        .divisor
+    }
+    pub fn is_satisfied(&self, x: i128) -> bool {
+        x % self.modulus == self.divisor
+    }
+}
+impl std::fmt::Display for Equation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "x ≡ {} (mod {})", self.divisor, self.modulus)
+    }
+}
+impl std::fmt::Debug for Equation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "x ≡ {} (mod {})", self.divisor, self.modulus)
+    }
+}
+
+/// The inverse of `a` modulo `modulus`, if they are coprime.
+pub fn mod_inverse(a: i128, modulus: i128) -> Option<i128> {
+    let a = Equation::new(a, modulus).divisor;
+    let (m1, m2) = bezout_identity(a, modulus);
+    if m1 * a + m2 * modulus == 1 {
+        Some(Equation::new(m1, modulus).divisor)
+    } else {
+        None
+    }
+}
+
+/// Computes `base ^ exponent (mod modulus)` by repeated squaring.
+pub fn mod_pow(base: i128, exponent: u64, modulus: i128) -> i128 {
+    let mut output = 1 % modulus;
+    let mut base = Equation::new(base, modulus).divisor;
+    let mut exponent = exponent;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            output = output * base % modulus;
+        }
+        base = base * base % modulus;
+        exponent >>= 1;
+    }
+    output
+}
+
+/// Finds the smallest `x` such that `base ^ x ≡ target (mod modulus)` by trying every power in
+/// turn.
+pub fn discrete_log_naive(base: i128, target: i128, modulus: i128) -> Option<u64> {
+    let target = Equation::new(target, modulus).divisor;
+    let base = Equation::new(base, modulus).divisor;
+    let mut value = 1 % modulus;
+    for x in 0..modulus as u64 {
+        if value == target {
+            return Some(x);
+        }
+        value = value * base % modulus;
+    }
+    None
+}
+
+/// Finds the smallest `x` such that `base ^ x ≡ target (mod modulus)` using baby-step
+/// giant-step, which needs `base` to be invertible.
+pub fn discrete_log(base: i128, target: i128, modulus: i128) -> Option<u64> {
+    let target = Equation::new(target, modulus).divisor;
+    let base = Equation::new(base, modulus).divisor;
+    let steps = (modulus as f64).sqrt().ceil() as u64;
+    let mut baby_steps = HashMap::new();
+    let mut value = 1 % modulus;
+    for j in 0..steps {
+        baby_steps.entry(value).or_insert(j);
+        value = value * base % modulus;
+    }
+    let giant_step = mod_inverse(mod_pow(base, steps, modulus), modulus)?;
+    let mut value = target;
+    for i in 0..steps {
+        if let Some(j) = baby_steps.get(&value) {
+            return Some(i * steps + j);
+        }
+        value = value * giant_step % modulus;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gets_bezout() {
+        fn assert_identity(n1: i128, n2: i128) {
+            let (m1, m2) = bezout_identity(n1, n2);
+            assert_eq!(1, m1 * n1 + m2 * n2)
+        }
+        assert_identity(5, 12);
+        assert_identity(3, 4);
+    }
+
+    #[test]
+    fn finds_wikipedia_example() {
+        let u1 = Equation {
+            divisor: 0,
+            modulus: 3,
+        };
+        let u2 = Equation {
+            divisor: 3,
+            modulus: 4,
+        };
+        let u3 = Equation {
+            divisor: 4,
+            modulus: 5,
+        };
+        let u4 = Equation::reduce(&u1, &u2);
+        let u5 = Equation::reduce(&u3, &u4);
+        assert!(u1.is_satisfied(u4.divisor));
+        assert!(u2.is_satisfied(u4.divisor));
+        assert!(u3.is_satisfied(u5.divisor));
+        assert_eq!(39, u5.divisor)
+    }
+    #[test]
+    fn solves_wikipedia_example() {
+        let eqs = [
+            Equation {
+                divisor: 0,
+                modulus: 3,
+            },
+            Equation {
+                divisor: 3,
+                modulus: 4,
+            },
+            Equation {
+                divisor: 4,
+                modulus: 5,
+            },
+        ];
+        assert_eq!(39, Equation::solve_set(eqs.iter().copied()))
+    }
+
+    #[test]
+    fn gets_inverse() {
+        assert_eq!(Some(4), mod_inverse(3, 11));
+        assert_eq!(Some(10), mod_inverse(-1, 11));
+        assert_eq!(None, mod_inverse(4, 12));
+    }
+    #[test]
+    fn gets_power() {
+        assert_eq!(1, mod_pow(7, 0, 13));
+        assert_eq!(445, mod_pow(4, 13, 497));
+        assert_eq!(0, mod_pow(5, 3, 1));
+        assert_eq!(mod_pow(2, 10, 1000), 1024 % 1000);
+    }
+    #[test]
+    fn gets_discrete_log() {
+        for &(base, modulus) in &[(3i128, 7i128), (2, 11), (7, 20201227)] {
+            for &x in &[0u64, 1, 4, 5] {
+                let target = mod_pow(base, x, modulus);
+                assert_eq!(Some(x), discrete_log_naive(base, target, modulus));
+                assert_eq!(Some(x), discrete_log(base, target, modulus));
+            }
+        }
+    }
+    #[test]
+    fn gets_discrete_log_of_negative_base() {
+        // -4 ≡ 7 (mod 11)
+        for &x in &[0u64, 1, 3, 7] {
+            let target = mod_pow(-4, x, 11);
+            assert_eq!(target, mod_pow(7, x, 11));
+            assert_eq!(Some(x), discrete_log_naive(-4, target, 11));
+            assert_eq!(Some(x), discrete_log(-4, target, 11));
+        }
+    }
+    #[test]
+    fn finds_no_discrete_log() {
+        // powers of 2 (mod 7) are 1, 2, 4
+        assert_eq!(None, discrete_log_naive(2, 3, 7));
+        assert_eq!(None, discrete_log(2, 3, 7));
+    }
+}